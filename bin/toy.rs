@@ -1,35 +1,159 @@
-use std::{env, process};
+use std::{
+    env, fs,
+    io::{self, Read},
+    process,
+};
 
-use toy_lisp;
+// 命令行参数错误，以及无法读取脚本时的退出状态（参考 sysexits.h）
+const EXIT_USAGE_ERROR: i32 = 64;
+const EXIT_IO_ERROR: i32 = 66;
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
-    if args.len() != 2 {
-        println!(
-            "\
+const USAGE: &str = "\
 usage:
 
-$ cargo run -- --repl
+$ toy [options] <script-file> [args...]
+$ toy [options] -e '<expr>' [-- args...]
+$ toy [options] - [args...]
+$ toy --repl
+
+options:
+  -e <expr>      evaluate the expression instead of a script file
+  -              read the script from stdin
+  --             pass the remaining arguments to the script as *argv*
+  -q, --quiet    do not print the \"eval script file:\" banner
+  --repl         enter the REPL
+  -h, --help     print this help
+  -V, --version  print the version
 
-or
+exit status:
+  0   success
+  1   runtime error
+  2   parse error
+  64  invalid command line arguments
+  66  cannot read the script file
 
-$ cargo run -- path-to-script-file
 e.g.
-$ cargo run -- example/01-add.cjs
-"
-        );
-        process::exit(1);
+$ cargo run -- example/02-fib.cjs
+$ cargo run -- -e '(add 1 2)'
+";
+
+// 程序的来源
+enum Source {
+    File(String),
+    Stdin,
+    Expr(String),
+}
+
+struct Options {
+    source: Option<Source>,
+    repl: bool,
+    quiet: bool,
+    argv: Vec<String>,
+}
+
+// 脚本文件（或者 `-`）后面的参数都传给脚本，这样脚本自身的参数不会被当作 toy 的选项，
+// 紧跟其后的 `--` 可以省略
+fn script_args<'a>(iter: &mut impl Iterator<Item = &'a String>) -> Vec<String> {
+    let rest: Vec<String> = iter.cloned().collect();
+    match rest.split_first() {
+        Some((first, others)) if first == "--" => others.to_vec(),
+        _ => rest,
     }
+}
 
-    let arg = args[1].as_str();
+fn parse_args(args: &[String]) -> Result<Options, String> {
+    let mut options = Options {
+        source: None,
+        repl: false,
+        quiet: false,
+        argv: vec![],
+    };
 
-    match arg {
-        "--repl" => {
-            toy_lisp::repl();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                print!("{}", USAGE);
+                process::exit(0);
+            }
+            "-V" | "--version" => {
+                println!("toy {}", env!("CARGO_PKG_VERSION"));
+                process::exit(0);
+            }
+            "-q" | "--quiet" => options.quiet = true,
+            "--repl" => options.repl = true,
+            "--" => options.argv.extend(iter.by_ref().cloned()),
+            _ if options.source.is_some() => {
+                return Err(format!("unexpected argument: {} (use -- to pass arguments)", arg))
+            }
+            "-e" => match iter.next() {
+                Some(expr) => options.source = Some(Source::Expr(expr.clone())),
+                None => return Err("option -e requires an expression".to_string()),
+            },
+            "-" => {
+                options.source = Some(Source::Stdin);
+                options.argv.extend(script_args(&mut iter));
+            }
+            _ if arg.starts_with('-') => return Err(format!("unknown option: {}", arg)),
+            _ => {
+                options.source = Some(Source::File(arg.clone()));
+                options.argv.extend(script_args(&mut iter));
+            }
         }
-        _ => {
-            println!("eval script file: {}", arg);
-            toy_lisp::run(arg);
+    }
+
+    if options.repl && options.source.is_some() {
+        return Err("--repl cannot be used with a script".to_string());
+    }
+    // REPL 不运行脚本，所以也不接受传给脚本的参数
+    if options.repl && !options.argv.is_empty() {
+        return Err("--repl cannot be used with script arguments".to_string());
+    }
+    if !options.repl && options.source.is_none() {
+        return Err("expected a script file, `-` or -e '<expr>'".to_string());
+    }
+
+    Ok(options)
+}
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    let options = match parse_args(&args) {
+        Ok(options) => options,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            process::exit(EXIT_USAGE_ERROR);
+        }
+    };
+
+    if options.repl {
+        process::exit(toy_lisp::repl());
+    }
+
+    let program = match options.source {
+        Some(Source::File(path)) => {
+            if !options.quiet {
+                println!("eval script file: {}", path);
+            }
+            fs::read_to_string(&path).map_err(|err| format!("{}: {}", path, err))
+        }
+        Some(Source::Stdin) => {
+            let mut text = String::new();
+            io::stdin()
+                .read_to_string(&mut text)
+                .map(|_| text)
+                .map_err(|err| format!("stdin: {}", err))
+        }
+        Some(Source::Expr(expr)) => Ok(expr),
+        None => unreachable!(),
+    };
+
+    match program {
+        Ok(program) => process::exit(toy_lisp::run(&program, &options.argv)),
+        Err(message) => {
+            eprintln!("read script failed: {}", message);
+            process::exit(EXIT_IO_ERROR);
         }
     }
 }
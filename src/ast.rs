@@ -1,30 +1,177 @@
 use crate::{env::Environment, error::Error};
 use core::fmt;
-use std::{cell::RefCell, rc::{Weak, Rc}};
+use im_rc::{OrdMap, OrdSet, Vector};
+use std::{
+    cell::RefCell,
+    cmp::Ordering,
+    collections::HashMap,
+    hash::{Hash, Hasher},
+    rc::{Rc, Weak},
+};
 
 // AST 的节点跟求值后数据共用一个枚举类型
 // Environment 的记录也是共用这个枚举类型
 #[derive(Clone)]
 pub enum Object {
+    Nil,                 // 空值，表示 "没有值"
     Symbol(String),      // 标识符（identifier）或者关键字（如 if, let, fn 等）
     Bool(bool),          // 布尔型
     Number(i64),         // 整数
+    Char(char),          // 字符
+    String(String),      // 字符串
+    Keyword(Keyword),    // 关键字，如 `:name`，求值结果为其自身
     List(Vec<Object>),   // 子列表
+    Vector(Vector<Object>), // 向量，持久化数据结构，修改时共享未改变的部分
+    Map(SourceOrdered<OrdMap<Object, Object>>), // 映射表（字典）
+    Set(SourceOrdered<OrdSet<Object>>),    // 集合
     Function(Box<Func>), // 函数
 }
 
+// 映射表和集合的数据
+//
+// 由 parser 构建的映射表和集合字面量还记录了各个键（或者元素）在源码里的顺序，
+// 使得求值时能够按源码的顺序对各项求值（见 eval），而不是按键排序之后的顺序。
+// 比较、排序以及哈希都只考虑数据本身，跟源码的顺序无关；
+// 其他方式构建的映射表和集合（比如求值的结果）不记录顺序。
+#[derive(Clone, Default)]
+pub struct SourceOrdered<T> {
+    items: T,
+    source_order: Option<Rc<Vec<Object>>>,
+}
+
+impl<T> SourceOrdered<T> {
+    // 记录源码顺序的映射表或者集合，order 为各个键（或者元素）在源码里的顺序
+    pub fn with_source_order(items: T, order: Vec<Object>) -> SourceOrdered<T> {
+        SourceOrdered {
+            items,
+            source_order: Some(Rc::new(order)),
+        }
+    }
+
+    // 各个键（或者元素）在源码里的顺序，不是由 parser 构建的则返回 None
+    pub fn source_order(&self) -> Option<&[Object]> {
+        self.source_order.as_deref().map(|order| order.as_slice())
+    }
+
+    pub fn into_inner(self) -> T {
+        self.items
+    }
+}
+
+impl<T> From<T> for SourceOrdered<T> {
+    fn from(items: T) -> Self {
+        SourceOrdered {
+            items,
+            source_order: None,
+        }
+    }
+}
+
+impl<T, A> FromIterator<A> for SourceOrdered<T>
+where
+    T: FromIterator<A>,
+{
+    fn from_iter<I: IntoIterator<Item = A>>(iter: I) -> Self {
+        SourceOrdered::from(iter.into_iter().collect::<T>())
+    }
+}
+
+impl<T> std::ops::Deref for SourceOrdered<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.items
+    }
+}
+
+impl<'a, T> IntoIterator for &'a SourceOrdered<T>
+where
+    &'a T: IntoIterator,
+{
+    type Item = <&'a T as IntoIterator>::Item;
+    type IntoIter = <&'a T as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.into_iter()
+    }
+}
+
+impl<T: Ord> Ord for SourceOrdered<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.items.cmp(&other.items)
+    }
+}
+
+impl<T: Ord> PartialOrd for SourceOrdered<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Ord> PartialEq for SourceOrdered<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T: Ord> Eq for SourceOrdered<T> {}
+
+impl<T: Hash> Hash for SourceOrdered<T> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.items.hash(state);
+    }
+}
+
+// 关键字
+//
+// 同名的关键字在全局只保存一份名称，关键字本身只记录名称的序号，
+// 所以比较两个关键字是否相等只需比较两个整数。
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Keyword(usize);
+
+#[derive(Default)]
+struct KeywordTable {
+    ids: HashMap<String, usize>,
+    names: Vec<Rc<str>>,
+}
+
+thread_local! {
+    static KEYWORD_TABLE: RefCell<KeywordTable> = RefCell::new(KeywordTable::default());
+}
+
+impl Keyword {
+    // 获取指定名称（不包括前缀冒号）的关键字，名称首次出现时登记到关键字表
+    pub fn intern(name: &str) -> Keyword {
+        KEYWORD_TABLE.with(|table| {
+            let mut table = table.borrow_mut();
+            if let Some(id) = table.ids.get(name) {
+                return Keyword(*id);
+            }
+
+            let id = table.names.len();
+            table.names.push(Rc::from(name));
+            table.ids.insert(name.to_string(), id);
+            Keyword(id)
+        })
+    }
+
+    // 关键字的名称（不包括前缀冒号）
+    pub fn name(&self) -> Rc<str> {
+        KEYWORD_TABLE.with(|table| Rc::clone(&table.borrow().names[self.0]))
+    }
+}
+
 #[derive(Clone)]
 pub enum Func {
     // 内置函数
-    // fn (param: &[Object]) -> Result<Object, Error> {...}
-    Builtin(fn(&[Object]) -> Result<Object, Error>),
+    // name, fn (param: &[Object]) -> Result<Object, Error> {...}
+    Builtin(&'static str, fn(&[Object]) -> Result<Object, Error>),
 
     // 用户自定义函数
-    // name, params, body, static scope environment
+    // name, clauses, static scope environment
     UserDefined(
         String,
-        Vec<String>,
-        Object,
+        Vec<Clause>,
 
         // 用户自定义函数无法绑定动态产生的作用域，比如在 defn 里面定义 defn 并返回该函数，
         // 该函数离开外层的 defn 之后，随着外层的 defn 的作用域结束，该函数所绑定的
@@ -35,8 +182,115 @@ pub enum Func {
     ),
 
     // 匿名函数
-    // params, body, static scope environment
-    Closure(Vec<String>, Object, Rc<RefCell<Option<Environment>>>),
+    // clauses, static scope environment
+    // 注：clauses 放在 Rc 里，每次对 fn 表达式求值都会产生新的 Rc，
+    // 函数的副本共享同一个 Rc，所以可以据此区分不同的函数（见 Func::identity）
+    Closure(Rc<Vec<Clause>>, Rc<RefCell<Option<Environment>>>),
+}
+
+// 函数的一个分支，即一组参数列表及其函数体
+//
+// 函数可以有多个分支，如 (defn greet ((name) ...) ((greeting name) ...))，
+// 调用时根据实参的个数选择对应的分支。
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Clause {
+    pub params: Params,
+    pub body: Object,
+}
+
+// 分支的源码，其中的字面量以可读的形式显示（比如字符串带双引号），
+// 所以函数的源码跟书写时一致
+impl fmt::Display for Clause {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "({}) {}", self.params, self.body.to_readable_string())
+    }
+}
+
+// 函数所有分支的源码，单个分支时省略分支外层的括号
+pub(crate) fn clauses_to_string(clauses: &[Clause]) -> String {
+    match clauses {
+        [clause] => clause.to_string(),
+        _ => {
+            let ss: Vec<String> = clauses.iter().map(|c| format!("({})", c)).collect();
+            ss.join(" ")
+        }
+    }
+}
+
+// 用户自定义函数和匿名函数的参数列表
+//
+// e.g. (a b &optional (c 10) d &rest others)
+//      (a b &rest others &key (size 1) color)
+// 依次为必选参数、可选参数、剩余参数以及关键字参数，后三者均可省略，
+// 但可选参数和关键字参数不能同时使用。
+// 可选参数和关键字参数可以指定默认值的表达式，省略时默认值为 nil。
+// 必选参数和可选参数可以是解构的模式，如 ((a b) {:keys (name)})，详见 pattern 模块。
+#[derive(Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Params {
+    pub required: Vec<Object>,
+    pub optional: Vec<(Object, Object)>,
+    pub rest: Option<String>,
+    pub keys: Vec<(String, Object)>,
+}
+
+impl Params {
+    // 最少的实参个数
+    pub fn min_arity(&self) -> usize {
+        self.required.len()
+    }
+
+    // 最多的实参个数，None 表示不限
+    // 每个关键字参数占用两个实参，即关键字及其值
+    pub fn max_arity(&self) -> Option<usize> {
+        match self.rest {
+            Some(_) => None,
+            None => Some(self.required.len() + self.optional.len() + self.keys.len() * 2),
+        }
+    }
+
+    // 是否接受指定个数的实参
+    pub fn accepts(&self, count: usize) -> bool {
+        count >= self.min_arity() && self.max_arity().is_none_or(|max| count <= max)
+    }
+
+    // 用于参数个数错误的信息，如 "2"、"1 to 3"、"at least 1"
+    pub fn arity_description(&self) -> String {
+        match self.max_arity() {
+            Some(max) if max == self.min_arity() => max.to_string(),
+            Some(max) => format!("{} to {}", self.min_arity(), max),
+            None => format!("at least {}", self.min_arity()),
+        }
+    }
+}
+
+impl fmt::Display for Params {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        // 模式和默认值以可读的形式显示，跟 Clause 的函数体一致
+        let with_default = |name: String, default: &Object| match default {
+            Object::Nil => name,
+            _ => format!("({} {})", name, default.to_readable_string()),
+        };
+
+        let mut ss: Vec<String> = self.required.iter().map(|p| p.to_readable_string()).collect();
+        if !self.optional.is_empty() {
+            ss.push("&optional".to_string());
+            ss.extend(self.optional.iter().map(|(p, d)| match p {
+                // 可选参数的解构模式必须写成 (pattern default) 的形式
+                Object::Symbol(_) => with_default(p.to_string(), d),
+                _ => format!("({} {})", p.to_readable_string(), d.to_readable_string()),
+            }));
+        }
+        if let Some(rest) = &self.rest {
+            ss.push("&rest".to_string());
+            ss.push(rest.clone());
+        }
+        if !self.keys.is_empty() {
+            ss.push("&key".to_string());
+            ss.extend(self.keys.iter().map(|(k, d)| with_default(k.clone(), d)));
+        }
+
+        write!(formatter, "{}", ss.join(" "))
+    }
 }
 
 // 实现 Display trait 能自动获得 ToString，
@@ -44,20 +298,39 @@ pub enum Func {
 impl fmt::Display for Object {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
         let s = match self {
+            Object::Nil => "nil".to_string(),
             Object::Symbol(s) => s.clone(),     // 标识符和关键字以字符串原样返回
             Object::Number(n) => n.to_string(), // 数字转换为字符串返回
             Object::Bool(b) => b.to_string(),   // 布尔型转为字符串返回
+            Object::Char(c) => c.to_string(),   // 字符和字符串以原样返回
+            Object::String(s) => s.clone(),
+            Object::Keyword(k) => format!(":{}", k.name()),
             Object::List(l) => {
                 let ss: Vec<String> = l.iter().map(|x| x.to_string()).collect();
                 format!("({})", ss.join(" "))
             }
+            Object::Vector(v) => {
+                let ss: Vec<String> = v.iter().map(|x| x.to_string()).collect();
+                format!("[{}]", ss.join(" "))
+            }
+            Object::Map(m) => {
+                let ss: Vec<String> = m
+                    .iter()
+                    .map(|(k, v)| format!("{} {}", k, v))
+                    .collect();
+                format!("{{{}}}", ss.join(" "))
+            }
+            Object::Set(s) => {
+                let ss: Vec<String> = s.iter().map(|x| x.to_string()).collect();
+                format!("#{{{}}}", ss.join(" "))
+            }
             Object::Function(f) => match f.as_ref() {
-                Func::Builtin(_) => "(builtin)".to_string(),
-                Func::UserDefined(name, params, body, _) => {
-                    format!("(defn {} ({}) {})", name, params.join(" "), body)
+                Func::Builtin(..) => "(builtin)".to_string(),
+                Func::UserDefined(name, clauses, _) => {
+                    format!("(defn {} {})", name, clauses_to_string(clauses))
                 },
-                Func::Closure(params, body, _) => {
-                    format!("(fn ({}) {})", params.join(" "), body)
+                Func::Closure(clauses, _) => {
+                    format!("(fn {})", clauses_to_string(clauses))
                 }
             },
         };
@@ -66,9 +339,213 @@ impl fmt::Display for Object {
     }
 }
 
+// 错误信息需要显示数据的值，比如 Error::UserError
+impl fmt::Debug for Object {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{}", self)
+    }
+}
+
+impl Object {
+    // 数据类型的名称，用于错误信息
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Object::Nil => "nil",
+            Object::Symbol(_) => "symbol",
+            Object::Bool(_) => "bool",
+            Object::Number(_) => "number",
+            Object::Char(_) => "char",
+            Object::String(_) => "string",
+            Object::Keyword(_) => "keyword",
+            Object::List(_) => "list",
+            Object::Vector(_) => "vector",
+            Object::Map(_) => "map",
+            Object::Set(_) => "set",
+            Object::Function(_) => "function",
+        }
+    }
+
+    // 可读形式的字符串，跟 Display 的区别是字符串带双引号并转义，字符以 `#\` 开头，
+    // 除了函数之外，可读形式被重新解析之后跟原来的数据相等，即 read(x.to_readable_string()) == x。
+    // 用于 REPL 输出求值结果、内置函数 pr-str 以及 format 的 `{:?}` 占位符
+    pub fn to_readable_string(&self) -> String {
+        match self {
+            Object::Char(c) => match c {
+                ' ' => "#\\space".to_string(),
+                '\n' => "#\\newline".to_string(),
+                '\t' => "#\\tab".to_string(),
+                _ if c.is_control() || c.is_whitespace() => format!("#\\u{{{:X}}}", *c as u32),
+                _ => format!("#\\{}", c),
+            },
+            Object::String(s) => {
+                let mut text = String::from('"');
+                for c in s.chars() {
+                    match c {
+                        '"' => text.push_str("\\\""),
+                        '\\' => text.push_str("\\\\"),
+                        '\n' => text.push_str("\\n"),
+                        '\t' => text.push_str("\\t"),
+                        '\r' => text.push_str("\\r"),
+                        _ => text.push(c),
+                    }
+                }
+                text.push('"');
+                text
+            }
+            Object::List(l) => {
+                let ss: Vec<String> = l.iter().map(|x| x.to_readable_string()).collect();
+                format!("({})", ss.join(" "))
+            }
+            Object::Vector(v) => {
+                let ss: Vec<String> = v.iter().map(|x| x.to_readable_string()).collect();
+                format!("[{}]", ss.join(" "))
+            }
+            Object::Map(m) => {
+                let ss: Vec<String> = m
+                    .iter()
+                    .map(|(k, v)| format!("{} {}", k.to_readable_string(), v.to_readable_string()))
+                    .collect();
+                format!("{{{}}}", ss.join(" "))
+            }
+            Object::Set(s) => {
+                let ss: Vec<String> = s.iter().map(|x| x.to_readable_string()).collect();
+                format!("#{{{}}}", ss.join(" "))
+            }
+            _ => self.to_string(),
+        }
+    }
+}
+
+// 映射表的键和集合的元素要求 Object 能够比较大小及相等。
+//
+// 不同类型的数据按类型排序，相同类型的数据按值比较（即结构相等），
+// 函数则按 "身份" 比较，只有同一个函数定义的副本才相等。
+impl Object {
+    fn type_rank(&self) -> u8 {
+        match self {
+            Object::Nil => 0,
+            Object::Symbol(_) => 1,
+            Object::Bool(_) => 2,
+            Object::Number(_) => 3,
+            Object::Char(_) => 4,
+            Object::String(_) => 5,
+            Object::Keyword(_) => 6,
+            Object::List(_) => 7,
+            Object::Vector(_) => 8,
+            Object::Map(_) => 9,
+            Object::Set(_) => 10,
+            Object::Function(_) => 11,
+        }
+    }
+}
+
+impl Ord for Object {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Object::Symbol(a), Object::Symbol(b)) => a.cmp(b),
+            (Object::Bool(a), Object::Bool(b)) => a.cmp(b),
+            (Object::Number(a), Object::Number(b)) => a.cmp(b),
+            (Object::Char(a), Object::Char(b)) => a.cmp(b),
+            (Object::String(a), Object::String(b)) => a.cmp(b),
+            // 相同的关键字只需比较序号，不同的关键字按名称排序
+            (Object::Keyword(a), Object::Keyword(b)) if a == b => Ordering::Equal,
+            (Object::Keyword(a), Object::Keyword(b)) => a.name().cmp(&b.name()),
+            (Object::List(a), Object::List(b)) => a.cmp(b),
+            (Object::Vector(a), Object::Vector(b)) => a.cmp(b),
+            (Object::Map(a), Object::Map(b)) => a.cmp(b),
+            (Object::Set(a), Object::Set(b)) => a.cmp(b),
+            (Object::Function(a), Object::Function(b)) => a.cmp(b),
+            _ => self.type_rank().cmp(&other.type_rank()),
+        }
+    }
+}
+
+impl PartialOrd for Object {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Object {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Object {}
+
+impl Hash for Object {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.type_rank().hash(state);
+        match self {
+            Object::Nil => {}
+            Object::Symbol(s) => s.hash(state),
+            Object::Bool(b) => b.hash(state),
+            Object::Number(n) => n.hash(state),
+            Object::Char(c) => c.hash(state),
+            Object::String(s) => s.hash(state),
+            Object::Keyword(k) => k.hash(state),
+            Object::List(l) => l.hash(state),
+            Object::Vector(v) => v.hash(state),
+            Object::Map(m) => m.hash(state),
+            Object::Set(s) => s.hash(state),
+            Object::Function(f) => f.hash(state),
+        }
+    }
+}
+
+impl Func {
+    // 函数的身份：内置函数为函数指针的地址，用户自定义函数为所绑定的作用域的地址
+    // （同一个作用域里不能重复定义同名的函数），
+    // 匿名函数为其 clauses 的地址，即每次对 fn 表达式求值产生的函数都不相同
+    fn identity(&self) -> usize {
+        match self {
+            Func::Builtin(_, f) => *f as usize,
+            Func::UserDefined(_, _, env) => env.as_ptr() as usize,
+            Func::Closure(clauses, _) => Rc::as_ptr(clauses) as usize,
+        }
+    }
+}
+
+impl Ord for Func {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Func::UserDefined(n1, c1, _), Func::UserDefined(n2, c2, _)) => (n1, c1)
+                .cmp(&(n2, c2))
+                .then(self.identity().cmp(&other.identity())),
+            (Func::Closure(..), Func::Closure(..)) => self.identity().cmp(&other.identity()),
+            (Func::Builtin(..), Func::Builtin(..)) => self.identity().cmp(&other.identity()),
+            (Func::Builtin(..), _) => Ordering::Less,
+            (_, Func::Builtin(..)) => Ordering::Greater,
+            (Func::UserDefined(..), _) => Ordering::Less,
+            (_, Func::UserDefined(..)) => Ordering::Greater,
+        }
+    }
+}
+
+impl PartialOrd for Func {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Func {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Func {}
+
+impl Hash for Func {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.identity().hash(state);
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::ast::Object;
+    use crate::ast::{Keyword, Object};
 
     #[test]
     fn test_number_to_string() {
@@ -91,4 +568,45 @@ mod tests {
         let v = Object::List(vec![v1, v2, v3]);
         assert_eq!(v.to_string().as_str(), "(foo 123 true)");
     }
+
+    #[test]
+    fn test_map_and_set_to_string() {
+        let m = Object::Map(
+            vec![
+                (Object::Number(2), Object::Bool(false)),
+                (Object::Number(1), Object::Bool(true)),
+            ]
+            .into_iter()
+            .collect(),
+        );
+        assert_eq!(m.to_string().as_str(), "{1 true 2 false}");
+
+        let s = Object::Set(vec![Object::Number(2), Object::Number(1)].into_iter().collect());
+        assert_eq!(s.to_string().as_str(), "#{1 2}");
+    }
+
+    #[test]
+    fn test_structural_equal() {
+        let v1 = Object::List(vec![Object::Number(1), Object::Symbol("foo".to_string())]);
+        let v2 = Object::List(vec![Object::Number(1), Object::Symbol("foo".to_string())]);
+        assert!(v1 == v2);
+        assert!(v1 != Object::Vector(vec![Object::Number(1)].into_iter().collect()));
+        assert!(Object::Number(1) != Object::Bool(true));
+    }
+
+    #[test]
+    fn test_keyword_intern() {
+        let k1 = Keyword::intern("name");
+        let k2 = Keyword::intern("name");
+        let k3 = Keyword::intern("age");
+        assert_eq!(k1, k2);
+        assert_ne!(k1, k3);
+        assert_eq!(Object::Keyword(k1).to_string().as_str(), ":name");
+    }
+
+    #[test]
+    fn test_vector_to_string() {
+        let v = Object::Vector(vec![Object::Number(1), Object::Number(2)].into_iter().collect());
+        assert_eq!(v.to_string().as_str(), "[1 2]");
+    }
 }
@@ -1,23 +1,29 @@
 use std::cell::RefCell;
 use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
 use std::rc::Rc;
 
-use crate::ast::{Func, Object};
+use im_rc::{OrdMap, OrdSet, Vector};
+
+use crate::ast::{Func, Keyword, Object, Params};
 use crate::error::Error;
+use crate::eval::read_from_string;
+use crate::output::{write_error_output, write_output};
 
 pub struct Environment {
     records: HashMap<String, Object>,
     // parent: Option<&'a Environment<'a>>,
     parent: Rc<RefCell<Option<Environment>>>,
+    // 是否允许访问文件系统，由全局环境决定，子环境在创建时继承上层环境的值
+    io_enabled: bool,
 }
 
 impl Environment {
     pub fn new(parent: &Rc<RefCell<Option<Environment>>>) -> Environment {
         let records: HashMap<String, Object> = HashMap::new();
-        Environment {
-            records: records,
-            parent: Rc::clone(parent),
-        }
+        Environment::new_with_records(records, parent)
     }
 
     pub fn new_with_records(
@@ -25,75 +31,93 @@ impl Environment {
         parent: &Rc<RefCell<Option<Environment>>>,
     ) -> Environment {
         Environment {
-            records: records,
+            records,
             parent: Rc::clone(parent),
+            io_enabled: rc_env_io_enabled(parent),
         }
     }
 
     pub fn new_global() -> Environment {
         let mut records: HashMap<String, Object> = HashMap::new();
 
-        records.insert(
-            "add".to_string(),
-            Object::Function(Box::new(Func::Builtin(builtin_fn_add))),
-        );
-        records.insert(
-            "sub".to_string(),
-            Object::Function(Box::new(Func::Builtin(builtin_fn_sub))),
-        );
-        records.insert(
-            "mul".to_string(),
-            Object::Function(Box::new(Func::Builtin(builtin_fn_mul))),
-        );
-        records.insert(
-            "div".to_string(),
-            Object::Function(Box::new(Func::Builtin(builtin_fn_div))),
-        );
-
-        records.insert(
-            "gt".to_string(),
-            Object::Function(Box::new(Func::Builtin(builtin_fn_greater_than))),
-        );
-        records.insert(
-            "gte".to_string(),
-            Object::Function(Box::new(Func::Builtin(builtin_fn_greater_or_equal_to))),
-        );
-        records.insert(
-            "lt".to_string(),
-            Object::Function(Box::new(Func::Builtin(builtin_fn_less_than))),
-        );
-        records.insert(
-            "lte".to_string(),
-            Object::Function(Box::new(Func::Builtin(builtin_fn_less_or_equal_to))),
-        );
-        records.insert(
-            "eq".to_string(),
-            Object::Function(Box::new(Func::Builtin(builtin_fn_equal_to))),
-        );
-        records.insert(
-            "neq".to_string(),
-            Object::Function(Box::new(Func::Builtin(builtin_fn_not_equal_to))),
-        );
-
-        records.insert(
-            "and".to_string(),
-            Object::Function(Box::new(Func::Builtin(builtin_fn_and))),
-        );
-        records.insert(
-            "or".to_string(),
-            Object::Function(Box::new(Func::Builtin(builtin_fn_or))),
-        );
-        records.insert(
-            "not".to_string(),
-            Object::Function(Box::new(Func::Builtin(builtin_fn_not))),
-        );
+        insert_builtin(&mut records, "add", builtin_fn_add);
+        insert_builtin(&mut records, "sub", builtin_fn_sub);
+        insert_builtin(&mut records, "mul", builtin_fn_mul);
+        insert_builtin(&mut records, "div", builtin_fn_div);
+
+        insert_builtin(&mut records, "gt", builtin_fn_greater_than);
+        insert_builtin(&mut records, "gte", builtin_fn_greater_or_equal_to);
+        insert_builtin(&mut records, "lt", builtin_fn_less_than);
+        insert_builtin(&mut records, "lte", builtin_fn_less_or_equal_to);
+        insert_builtin(&mut records, "eq", builtin_fn_equal_to);
+        insert_builtin(&mut records, "neq", builtin_fn_not_equal_to);
+        insert_builtin(&mut records, "nil?", builtin_fn_is_nil);
+
+        insert_builtin(&mut records, "and", builtin_fn_and);
+        insert_builtin(&mut records, "or", builtin_fn_or);
+        insert_builtin(&mut records, "not", builtin_fn_not);
+
+        insert_builtin(&mut records, "vec", builtin_fn_vec);
+        insert_builtin(&mut records, "get", builtin_fn_get);
+        insert_builtin(&mut records, "assoc", builtin_fn_assoc);
+        insert_builtin(&mut records, "conj", builtin_fn_conj);
+        insert_builtin(&mut records, "count", builtin_fn_count);
+        insert_builtin(&mut records, "subvec", builtin_fn_subvec);
+
+        insert_builtin(&mut records, "hash-map", builtin_fn_hash_map);
+        insert_builtin(&mut records, "dissoc", builtin_fn_dissoc);
+        insert_builtin(&mut records, "contains?", builtin_fn_contains);
+        insert_builtin(&mut records, "keys", builtin_fn_keys);
+        insert_builtin(&mut records, "vals", builtin_fn_vals);
+        insert_builtin(&mut records, "merge", builtin_fn_merge);
+        insert_builtin(&mut records, "union", builtin_fn_union);
+        insert_builtin(&mut records, "intersection", builtin_fn_intersection);
+        insert_builtin(&mut records, "difference", builtin_fn_difference);
+
+        insert_builtin(&mut records, "char->int", builtin_fn_char_to_int);
+        insert_builtin(&mut records, "int->char", builtin_fn_int_to_char);
+        insert_builtin(&mut records, "char-upper", builtin_fn_char_upper);
+        insert_builtin(&mut records, "char-alphabetic?", builtin_fn_char_is_alphabetic);
+        insert_builtin(&mut records, "char-numeric?", builtin_fn_char_is_numeric);
+        insert_builtin(&mut records, "string->list", builtin_fn_string_to_list);
+        insert_builtin(&mut records, "list->string", builtin_fn_list_to_string);
+
+        insert_builtin(&mut records, "print", builtin_fn_print);
+        insert_builtin(&mut records, "println", builtin_fn_println);
+        insert_builtin(&mut records, "eprint", builtin_fn_eprint);
+        insert_builtin(&mut records, "eprintln", builtin_fn_eprintln);
+        insert_builtin(&mut records, "format", builtin_fn_format);
+        insert_builtin(&mut records, "pr-str", builtin_fn_pr_str);
+        insert_builtin(&mut records, "read-string", builtin_fn_read_string);
+
+        insert_builtin(&mut records, "type-of", builtin_fn_type_of);
+        insert_builtin(&mut records, "arity", builtin_fn_arity);
+        insert_builtin(&mut records, "throw", builtin_fn_throw);
+        insert_builtin(&mut records, "exit", builtin_fn_exit);
 
         Environment {
-            records: records,
+            records,
             parent: Rc::new(RefCell::new(None)),
+            io_enabled: false,
         }
     }
 
+    // 允许访问文件系统的全局环境，即在 new_global 的基础上增加文件读写的内置函数，并允许 load-file。
+    // 嵌入解释器运行不可信的程序时应该使用 new_global，此时程序无法读写文件。
+    pub fn new_global_with_io() -> Environment {
+        let mut env = Environment::new_global();
+
+        insert_builtin(&mut env.records, "slurp", builtin_fn_slurp);
+        insert_builtin(&mut env.records, "spit", builtin_fn_spit);
+        insert_builtin(&mut env.records, "read-lines", builtin_fn_read_lines);
+        insert_builtin(&mut env.records, "file-exists?", builtin_fn_file_exists);
+        insert_builtin(&mut env.records, "list-dir", builtin_fn_list_dir);
+        insert_builtin(&mut env.records, "delete-file", builtin_fn_delete_file);
+
+        env.io_enabled = true;
+        env
+    }
+
     pub fn to_rc_env(self) -> Rc<RefCell<Option<Environment>>> {
         Rc::new(RefCell::new(Some(self)))
     }
@@ -101,7 +125,7 @@ impl Environment {
     // 如果名称在当前 scope 里已经定义，则返回 Err
     pub fn define(&mut self, name: &str, obj: Object) -> Result<(), Error> {
         if self.records.contains_key(name) {
-            return Err(Error::EvalError("identifier already exists".to_string()));
+            return Err(Error::AlreadyDefined(name.to_string()));
         }
 
         let ns = name.to_string();
@@ -116,6 +140,24 @@ impl Environment {
             None => rc_env_lookup(&self.parent, name),
         }
     }
+
+    // 当前 scope 以及所有上层 scope 里定义的名称
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.records.keys().cloned().collect();
+        names.extend(rc_env_names(&self.parent));
+        names
+    }
+}
+
+fn insert_builtin(
+    records: &mut HashMap<String, Object>,
+    name: &'static str,
+    f: fn(&[Object]) -> Result<Object, Error>,
+) {
+    records.insert(
+        name.to_string(),
+        Object::Function(Box::new(Func::Builtin(name, f))),
+    );
 }
 
 pub fn rc_env_lookup(rc_env: &Rc<RefCell<Option<Environment>>>, name: &str) -> Option<Object> {
@@ -125,6 +167,21 @@ pub fn rc_env_lookup(rc_env: &Rc<RefCell<Option<Environment>>>, name: &str) -> O
     }
 }
 
+pub fn rc_env_names(rc_env: &Rc<RefCell<Option<Environment>>>) -> Vec<String> {
+    match rc_env.borrow().as_ref() {
+        Some(env) => env.names(),
+        None => vec![],
+    }
+}
+
+// 环境是否允许访问文件系统，见 Environment::new_global_with_io
+pub fn rc_env_io_enabled(rc_env: &Rc<RefCell<Option<Environment>>>) -> bool {
+    match rc_env.borrow().as_ref() {
+        Some(env) => env.io_enabled,
+        None => false,
+    }
+}
+
 pub fn rc_env_define(
     rc_env: &Rc<RefCell<Option<Environment>>>,
     name: &str,
@@ -135,28 +192,40 @@ pub fn rc_env_define(
             // env.records.insert(name.to_string(), obj);
             env.define(name, obj)
         }
-        None => Err(Error::EvalError("no outer environment".to_string())),
+        None => Err(Error::RuntimeError("no outer environment".to_string())),
     }
 }
 
 fn builtin_fn_add(objs: &[Object]) -> Result<Object, Error> {
     let (left, right) = parse_number_pair(objs)?;
-    Ok(Object::Number(left + right))
+    left.checked_add(right)
+        .map(Object::Number)
+        .ok_or(Error::ArithmeticError("integer overflow".to_string()))
 }
 
 fn builtin_fn_sub(objs: &[Object]) -> Result<Object, Error> {
     let (left, right) = parse_number_pair(objs)?;
-    Ok(Object::Number(left - right))
+    left.checked_sub(right)
+        .map(Object::Number)
+        .ok_or(Error::ArithmeticError("integer overflow".to_string()))
 }
 
 fn builtin_fn_mul(objs: &[Object]) -> Result<Object, Error> {
     let (left, right) = parse_number_pair(objs)?;
-    Ok(Object::Number(left * right))
+    left.checked_mul(right)
+        .map(Object::Number)
+        .ok_or(Error::ArithmeticError("integer overflow".to_string()))
 }
 
 fn builtin_fn_div(objs: &[Object]) -> Result<Object, Error> {
     let (left, right) = parse_number_pair(objs)?;
-    Ok(Object::Number(left / right))
+    if right == 0 {
+        return Err(Error::ArithmeticError("division by zero".to_string()));
+    }
+
+    left.checked_div(right)
+        .map(Object::Number)
+        .ok_or(Error::ArithmeticError("integer overflow".to_string()))
 }
 
 fn builtin_fn_greater_than(objs: &[Object]) -> Result<Object, Error> {
@@ -179,22 +248,33 @@ fn builtin_fn_less_or_equal_to(objs: &[Object]) -> Result<Object, Error> {
     Ok(Object::Bool(left <= right))
 }
 
+// 比较两个数据是否相等，支持所有类型的数据：
+// 列表、向量、映射表和集合按结构比较，函数则只跟同一个函数的副本相等
+// （每次对 fn 表达式求值都产生新的函数，见 Func::identity），
+// 不同类型的数据（包括 nil 跟其他数据）比较均返回 false
 fn builtin_fn_equal_to(objs: &[Object]) -> Result<Object, Error> {
-    match parse_number_pair(objs) {
-        Ok((left, right)) => Ok(Object::Bool(left == right)),
-        _ => match parse_bool_pair(objs) {
-            Ok((left, right)) => Ok(Object::Bool(left == right)),
-            Err(err) => Err(err),
-        },
+    if objs.len() != 2 {
+        return Err(Error::arity_error("2", objs.len()));
     }
+
+    Ok(Object::Bool(objs[0] == objs[1]))
 }
 
 fn builtin_fn_not_equal_to(objs: &[Object]) -> Result<Object, Error> {
     let obj = builtin_fn_equal_to(objs)?;
     match obj {
         Object::Bool(b) => Ok(Object::Bool(!b)),
-        _ => Err(Error::EvalError("unreach".to_string())),
+        _ => Err(Error::RuntimeError("unreach".to_string())),
+    }
+}
+
+// (nil? obj)
+fn builtin_fn_is_nil(objs: &[Object]) -> Result<Object, Error> {
+    if objs.len() != 1 {
+        return Err(Error::arity_error("1", objs.len()));
     }
+
+    Ok(Object::Bool(matches!(objs[0], Object::Nil)))
 }
 
 fn builtin_fn_and(objs: &[Object]) -> Result<Object, Error> {
@@ -209,48 +289,800 @@ fn builtin_fn_or(objs: &[Object]) -> Result<Object, Error> {
 
 fn builtin_fn_not(objs: &[Object]) -> Result<Object, Error> {
     if objs.len() != 1 {
-        return Err(Error::EvalError("required 1 arguments".to_string()));
+        return Err(Error::arity_error("1", objs.len()));
     }
 
-    let b = parse_bool(&objs[0])?;
+    let b = arg(objs, 0, parse_bool)?;
     Ok(Object::Bool(!b))
 }
 
+// (vec coll)
+// 把列表或者向量转换为向量
+fn builtin_fn_vec(objs: &[Object]) -> Result<Object, Error> {
+    if objs.len() != 1 {
+        return Err(Error::arity_error("1", objs.len()));
+    }
+
+    match &objs[0] {
+        Object::List(list) => Ok(Object::Vector(list.iter().cloned().collect())),
+        Object::Vector(_) => Ok(objs[0].clone()),
+        obj => Err(Error::type_error("list or vector", obj).at_argument(1)),
+    }
+}
+
+// (get vector index)
+// (get map key)
+// (get set item)
+// (get coll key default)
+// 当索引超出范围或者键不存在时，如果提供了 default 则返回 default，否则返回 nil
+fn builtin_fn_get(objs: &[Object]) -> Result<Object, Error> {
+    if objs.len() != 2 && objs.len() != 3 {
+        return Err(Error::arity_error("2 or 3", objs.len()));
+    }
+
+    let found = match &objs[0] {
+        Object::Vector(vector) => parse_index(&objs[1], vector.len(), false)
+            .ok()
+            .map(|index| vector[index].clone()),
+        Object::Map(map) => map.get(&objs[1]).cloned(),
+        Object::Set(set) => set.contains(&objs[1]).then(|| objs[1].clone()),
+        obj => return Err(Error::type_error("vector, map or set", obj).at_argument(1)),
+    };
+
+    match (found, objs.get(2)) {
+        (Some(obj), _) => Ok(obj),
+        (None, Some(default)) => Ok(default.clone()),
+        (None, None) => Ok(Object::Nil),
+    }
+}
+
+// (assoc vector index value)
+// 返回替换了指定位置元素的新向量，原向量保持不变。
+// 当 index 等于向量的长度时，相当于在末尾追加元素。
+//
+// (assoc map key1 value1 key2 value2 ...)
+// 返回添加（或者替换）了指定键值的新映射表
+fn builtin_fn_assoc(objs: &[Object]) -> Result<Object, Error> {
+    match objs.first() {
+        Some(Object::Map(map)) => {
+            let pairs = &objs[1..];
+            if pairs.is_empty() || !pairs.len().is_multiple_of(2) {
+                return Err(Error::arity_error("an odd number (at least 3) of", objs.len()));
+            }
+
+            let mut map = map.clone().into_inner();
+            for pair in pairs.chunks(2) {
+                map.insert(pair[0].clone(), pair[1].clone());
+            }
+            Ok(Object::Map(map.into()))
+        }
+        _ => {
+            if objs.len() != 3 {
+                return Err(Error::arity_error("3", objs.len()));
+            }
+
+            let mut vector = arg(objs, 0, parse_vector)?.clone();
+            let index = arg(objs, 1, |obj| parse_index(obj, vector.len(), true))?;
+
+            if index == vector.len() {
+                vector.push_back(objs[2].clone());
+            } else {
+                vector.set(index, objs[2].clone());
+            }
+
+            Ok(Object::Vector(vector))
+        }
+    }
+}
+
+// (conj vector value1 value2 ...)
+// 返回在末尾追加了元素的新向量
+//
+// (conj set item1 item2 ...)
+// 返回添加了元素的新集合
+fn builtin_fn_conj(objs: &[Object]) -> Result<Object, Error> {
+    let (first, rest) = objs
+        .split_first()
+        .ok_or(Error::arity_error("at least 1", 0))?;
+
+    match first {
+        Object::Set(set) => {
+            let mut set = set.clone().into_inner();
+            for obj in rest {
+                set.insert(obj.clone());
+            }
+            Ok(Object::Set(set.into()))
+        }
+        _ => {
+            let mut vector = arg(objs, 0, parse_vector)?.clone();
+            for obj in rest {
+                vector.push_back(obj.clone());
+            }
+            Ok(Object::Vector(vector))
+        }
+    }
+}
+
+// (count coll)
+fn builtin_fn_count(objs: &[Object]) -> Result<Object, Error> {
+    if objs.len() != 1 {
+        return Err(Error::arity_error("1", objs.len()));
+    }
+
+    let length = match &objs[0] {
+        Object::List(list) => list.len(),
+        Object::Vector(vector) => vector.len(),
+        Object::Map(map) => map.len(),
+        Object::Set(set) => set.len(),
+        Object::String(s) => s.chars().count(),
+        obj => return Err(Error::type_error("collection", obj).at_argument(1)),
+    };
+
+    Ok(Object::Number(length as i64))
+}
+
+// (subvec vector start)
+// (subvec vector start end)
+// 返回 [start, end) 范围内的元素组成的新向量
+fn builtin_fn_subvec(objs: &[Object]) -> Result<Object, Error> {
+    if objs.len() != 2 && objs.len() != 3 {
+        return Err(Error::arity_error("2 or 3", objs.len()));
+    }
+
+    let vector = arg(objs, 0, parse_vector)?;
+    let start = arg(objs, 1, |obj| parse_index(obj, vector.len(), true))?;
+    let end = match objs.len() {
+        3 => arg(objs, 2, |obj| parse_index(obj, vector.len(), true))?,
+        _ => vector.len(),
+    };
+
+    if start > end {
+        return Err(Error::ValueError(format!(
+            "subvec start index {} is greater than end index {}",
+            start, end
+        )));
+    }
+
+    Ok(Object::Vector(vector.clone().slice(start..end)))
+}
+
+// (hash-map key1 value1 key2 value2 ...)
+fn builtin_fn_hash_map(objs: &[Object]) -> Result<Object, Error> {
+    if !objs.len().is_multiple_of(2) {
+        return Err(Error::arity_error("an even number of", objs.len()));
+    }
+
+    let map: OrdMap<Object, Object> = objs
+        .chunks(2)
+        .map(|pair| (pair[0].clone(), pair[1].clone()))
+        .collect();
+    Ok(Object::Map(map.into()))
+}
+
+// (dissoc map key1 key2 ...)
+// 返回移除了指定键的新映射表
+fn builtin_fn_dissoc(objs: &[Object]) -> Result<Object, Error> {
+    if objs.is_empty() {
+        return Err(Error::arity_error("at least 1", 0));
+    }
+
+    let mut map = arg(objs, 0, parse_map)?.clone();
+    for key in &objs[1..] {
+        map.remove(key);
+    }
+
+    Ok(Object::Map(map.into()))
+}
+
+// (contains? map key)
+// (contains? set item)
+// (contains? vector index)
+fn builtin_fn_contains(objs: &[Object]) -> Result<Object, Error> {
+    if objs.len() != 2 {
+        return Err(Error::arity_error("2", objs.len()));
+    }
+
+    let b = match &objs[0] {
+        Object::Map(map) => map.contains_key(&objs[1]),
+        Object::Set(set) => set.contains(&objs[1]),
+        Object::Vector(vector) => parse_index(&objs[1], vector.len(), false).is_ok(),
+        obj => return Err(Error::type_error("vector, map or set", obj).at_argument(1)),
+    };
+
+    Ok(Object::Bool(b))
+}
+
+// (keys map)
+// 返回由所有键组成的列表
+fn builtin_fn_keys(objs: &[Object]) -> Result<Object, Error> {
+    if objs.len() != 1 {
+        return Err(Error::arity_error("1", objs.len()));
+    }
+
+    let map = arg(objs, 0, parse_map)?;
+    Ok(Object::List(map.keys().cloned().collect()))
+}
+
+// (vals map)
+// 返回由所有值组成的列表
+fn builtin_fn_vals(objs: &[Object]) -> Result<Object, Error> {
+    if objs.len() != 1 {
+        return Err(Error::arity_error("1", objs.len()));
+    }
+
+    let map = arg(objs, 0, parse_map)?;
+    Ok(Object::List(map.values().cloned().collect()))
+}
+
+// (merge map1 map2 ...)
+// 合并多个映射表，当键重复时，后面的映射表的值覆盖前面的
+fn builtin_fn_merge(objs: &[Object]) -> Result<Object, Error> {
+    let mut result = OrdMap::new();
+    for index in 0..objs.len() {
+        result = arg(objs, index, parse_map)?.clone().union(result);
+    }
+
+    Ok(Object::Map(result.into()))
+}
+
+// (union set1 set2 ...)
+fn builtin_fn_union(objs: &[Object]) -> Result<Object, Error> {
+    let mut result = OrdSet::new();
+    for index in 0..objs.len() {
+        result = result.union(arg(objs, index, parse_set)?.clone());
+    }
+
+    Ok(Object::Set(result.into()))
+}
+
+// (intersection set1 set2 ...)
+fn builtin_fn_intersection(objs: &[Object]) -> Result<Object, Error> {
+    if objs.is_empty() {
+        return Err(Error::arity_error("at least 1", 0));
+    }
+
+    let mut result = arg(objs, 0, parse_set)?.clone();
+    for index in 1..objs.len() {
+        result = result.intersection(arg(objs, index, parse_set)?.clone());
+    }
+
+    Ok(Object::Set(result.into()))
+}
+
+// (difference set1 set2 ...)
+// 返回在 set1 里但不在其余集合里的元素
+fn builtin_fn_difference(objs: &[Object]) -> Result<Object, Error> {
+    if objs.is_empty() {
+        return Err(Error::arity_error("at least 1", 0));
+    }
+
+    let mut result = arg(objs, 0, parse_set)?.clone();
+    for index in 1..objs.len() {
+        result = result.relative_complement(arg(objs, index, parse_set)?.clone());
+    }
+
+    Ok(Object::Set(result.into()))
+}
+
+// (char->int char)
+// 返回字符的 Unicode 码点
+fn builtin_fn_char_to_int(objs: &[Object]) -> Result<Object, Error> {
+    let c = parse_single_char(objs)?;
+    Ok(Object::Number(c as i64))
+}
+
+// (int->char number)
+fn builtin_fn_int_to_char(objs: &[Object]) -> Result<Object, Error> {
+    if objs.len() != 1 {
+        return Err(Error::arity_error("1", objs.len()));
+    }
+
+    let i = arg(objs, 0, parse_number)?;
+    u32::try_from(i)
+        .ok()
+        .and_then(char::from_u32)
+        .map(Object::Char)
+        .ok_or(Error::ValueError(format!("invalid code point: {}", i)))
+}
+
+// (char-upper char)
+// 当大写形式不是单独一个字符时（比如 `ß`），返回原字符
+fn builtin_fn_char_upper(objs: &[Object]) -> Result<Object, Error> {
+    let c = parse_single_char(objs)?;
+    let mut upper = c.to_uppercase();
+    match (upper.next(), upper.next()) {
+        (Some(u), None) => Ok(Object::Char(u)),
+        _ => Ok(Object::Char(c)),
+    }
+}
+
+// (char-alphabetic? char)
+fn builtin_fn_char_is_alphabetic(objs: &[Object]) -> Result<Object, Error> {
+    let c = parse_single_char(objs)?;
+    Ok(Object::Bool(c.is_alphabetic()))
+}
+
+// (char-numeric? char)
+fn builtin_fn_char_is_numeric(objs: &[Object]) -> Result<Object, Error> {
+    let c = parse_single_char(objs)?;
+    Ok(Object::Bool(c.is_numeric()))
+}
+
+// (string->list string)
+// 返回由字符串的所有字符组成的列表
+fn builtin_fn_string_to_list(objs: &[Object]) -> Result<Object, Error> {
+    if objs.len() != 1 {
+        return Err(Error::arity_error("1", objs.len()));
+    }
+
+    let s = arg(objs, 0, parse_string)?;
+    Ok(Object::List(s.chars().map(Object::Char).collect()))
+}
+
+// (list->string coll)
+// 把由字符组成的列表或者向量拼接为字符串
+fn builtin_fn_list_to_string(objs: &[Object]) -> Result<Object, Error> {
+    if objs.len() != 1 {
+        return Err(Error::arity_error("1", objs.len()));
+    }
+
+    let s = match &objs[0] {
+        Object::List(list) => list.iter().map(parse_char).collect::<Result<String, Error>>()?,
+        Object::Vector(vector) => vector
+            .iter()
+            .map(parse_char)
+            .collect::<Result<String, Error>>()?,
+        obj => return Err(Error::type_error("list or vector", obj).at_argument(1)),
+    };
+
+    Ok(Object::String(s))
+}
+
+// (print value...)
+// 把各个参数的显示形式以空格分隔输出到标准输出，返回 nil
+fn builtin_fn_print(objs: &[Object]) -> Result<Object, Error> {
+    write_output(&join_display(objs))?;
+    Ok(Object::Nil)
+}
+
+// (println value...)
+// 跟 print 相同，但在末尾输出换行符
+fn builtin_fn_println(objs: &[Object]) -> Result<Object, Error> {
+    write_output(&(join_display(objs) + "\n"))?;
+    Ok(Object::Nil)
+}
+
+// (eprint value...)
+// 跟 print 相同，但输出到标准错误
+fn builtin_fn_eprint(objs: &[Object]) -> Result<Object, Error> {
+    write_error_output(&join_display(objs))?;
+    Ok(Object::Nil)
+}
+
+// (eprintln value...)
+fn builtin_fn_eprintln(objs: &[Object]) -> Result<Object, Error> {
+    write_error_output(&(join_display(objs) + "\n"))?;
+    Ok(Object::Nil)
+}
+
+fn join_display(objs: &[Object]) -> String {
+    let ss: Vec<String> = objs.iter().map(|x| x.to_string()).collect();
+    ss.join(" ")
+}
+
+// (format template value...)
+// 按模板构建字符串，模板里的 `{}` 依次替换为参数的显示形式（跟 print 相同），
+// `{:?}` 替换为参数的可读形式（字符串带双引号，字符以 `#\` 开头），
+// `{{` 和 `}}` 分别表示花括号本身，占位符的个数必须跟参数的个数相同
+// e.g.
+// (format "{} + {} = {:?}" 1 2 "three") => "1 + 2 = \"three\""
+fn builtin_fn_format(objs: &[Object]) -> Result<Object, Error> {
+    if objs.is_empty() {
+        return Err(Error::arity_error("at least 1", 0));
+    }
+
+    let template = arg(objs, 0, parse_string)?;
+    let args = &objs[1..];
+
+    let mut text = String::new();
+    let mut count = 0; // 占位符的个数
+    let mut chars = template.chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' => {
+                let mut spec = String::new();
+                loop {
+                    match chars.next() {
+                        Some('{') if spec.is_empty() => break text.push('{'),
+                        Some('}') => {
+                            let readable = match spec.as_str() {
+                                "" => false,
+                                ":?" => true,
+                                _ => {
+                                    return Err(Error::ValueError(format!(
+                                        "invalid placeholder in format template: {{{}}}",
+                                        spec
+                                    )))
+                                }
+                            };
+
+                            if let Some(obj) = args.get(count) {
+                                if readable {
+                                    text.push_str(&obj.to_readable_string());
+                                } else {
+                                    text.push_str(&obj.to_string());
+                                }
+                            }
+                            count += 1;
+                            break;
+                        }
+                        Some(sc) => spec.push(sc),
+                        None => {
+                            return Err(Error::ValueError(
+                                "unclosed placeholder in format template, use {{ for a literal {".to_string(),
+                            ))
+                        }
+                    }
+                }
+            }
+            '}' => match chars.next() {
+                Some('}') => text.push('}'),
+                _ => {
+                    return Err(Error::ValueError(
+                        "unmatched } in format template, use }} for a literal }".to_string(),
+                    ))
+                }
+            },
+            _ => text.push(c),
+        }
+    }
+
+    if count != args.len() {
+        return Err(Error::ValueError(format!(
+            "format template has {} {}, got {} {}",
+            count,
+            if count == 1 { "placeholder" } else { "placeholders" },
+            args.len(),
+            if args.len() == 1 { "argument" } else { "arguments" }
+        )));
+    }
+
+    Ok(Object::String(text))
+}
+
+// (pr-str value...)
+// 把各个参数的可读形式以空格分隔组成字符串，
+// 跟 print 不同，字符串带双引号，字符以 `#\` 开头，所以结果可以被 read-string 重新读取
+// e.g.
+// (pr-str "a" #\b) => "\"a\" #\\b"
+fn builtin_fn_pr_str(objs: &[Object]) -> Result<Object, Error> {
+    let ss: Vec<String> = objs.iter().map(|x| x.to_readable_string()).collect();
+    Ok(Object::String(ss.join(" ")))
+}
+
+// (read-string text)
+// 解析字符串里的一个表达式（不求值），返回解析得到的数据
+// e.g.
+// (read-string "(add 1 2)") => (add 1 2)
+fn builtin_fn_read_string(objs: &[Object]) -> Result<Object, Error> {
+    if objs.len() != 1 {
+        return Err(Error::arity_error("1", objs.len()));
+    }
+
+    let text = arg(objs, 0, parse_string)?;
+    let mut objects = read_from_string(text)?;
+    if objects.len() != 1 {
+        return Err(Error::ValueError(format!(
+            "read-string expected exactly one form, got {}",
+            objects.len()
+        )));
+    }
+
+    Ok(objects.remove(0))
+}
+
+// (slurp path)
+// 读取文本文件的全部内容
+fn builtin_fn_slurp(objs: &[Object]) -> Result<Object, Error> {
+    if objs.len() != 1 {
+        return Err(Error::arity_error("1", objs.len()));
+    }
+
+    let path = arg(objs, 0, parse_string)?;
+    let text = fs::read_to_string(path).map_err(|err| io_error("cannot read file", path, err))?;
+    Ok(Object::String(text))
+}
+
+// (spit path content)
+// (spit path content :append true)
+// 把内容（字符串以原样，其他数据以 print 的形式）写入文件，文件已存在时覆盖原有的内容，
+// 或者当 :append 为 true 时追加到末尾，返回 nil
+fn builtin_fn_spit(objs: &[Object]) -> Result<Object, Error> {
+    if objs.len() != 2 && objs.len() != 4 {
+        return Err(Error::arity_error("2 or 4", objs.len()));
+    }
+
+    let path = arg(objs, 0, parse_string)?;
+    let content = objs[1].to_string();
+
+    let append = match objs.get(2) {
+        Some(Object::Keyword(k)) if *k == Keyword::intern("append") => arg(objs, 3, parse_bool)?,
+        Some(obj) => {
+            return Err(Error::ValueError(format!(
+                "unknown option for spit: {}, expected :append",
+                obj.to_readable_string()
+            )))
+        }
+        None => false,
+    };
+
+    let result = if append {
+        fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(content.as_bytes()))
+    } else {
+        fs::write(path, content)
+    };
+
+    result.map_err(|err| io_error("cannot write file", path, err))?;
+    Ok(Object::Nil)
+}
+
+// (read-lines path)
+// 读取文本文件，返回由各行（不包括换行符）组成的向量
+fn builtin_fn_read_lines(objs: &[Object]) -> Result<Object, Error> {
+    if objs.len() != 1 {
+        return Err(Error::arity_error("1", objs.len()));
+    }
+
+    let path = arg(objs, 0, parse_string)?;
+    let text = fs::read_to_string(path).map_err(|err| io_error("cannot read file", path, err))?;
+    Ok(Object::Vector(
+        text.lines().map(|line| Object::String(line.to_string())).collect(),
+    ))
+}
+
+// (file-exists? path)
+// 检查文件或者目录是否存在
+fn builtin_fn_file_exists(objs: &[Object]) -> Result<Object, Error> {
+    if objs.len() != 1 {
+        return Err(Error::arity_error("1", objs.len()));
+    }
+
+    let path = arg(objs, 0, parse_string)?;
+    Ok(Object::Bool(Path::new(path).exists()))
+}
+
+// (list-dir path)
+// 返回目录里的文件和子目录的名称组成的向量，按名称排序
+fn builtin_fn_list_dir(objs: &[Object]) -> Result<Object, Error> {
+    if objs.len() != 1 {
+        return Err(Error::arity_error("1", objs.len()));
+    }
+
+    let path = arg(objs, 0, parse_string)?;
+    let mut names = fs::read_dir(path)
+        .and_then(|entries| {
+            entries
+                .map(|entry| entry.map(|e| e.file_name().to_string_lossy().into_owned()))
+                .collect::<Result<Vec<String>, _>>()
+        })
+        .map_err(|err| io_error("cannot list directory", path, err))?;
+    names.sort();
+
+    Ok(Object::Vector(names.into_iter().map(Object::String).collect()))
+}
+
+// (delete-file path)
+// 删除文件，返回 nil
+fn builtin_fn_delete_file(objs: &[Object]) -> Result<Object, Error> {
+    if objs.len() != 1 {
+        return Err(Error::arity_error("1", objs.len()));
+    }
+
+    let path = arg(objs, 0, parse_string)?;
+    fs::remove_file(path).map_err(|err| io_error("cannot delete file", path, err))?;
+    Ok(Object::Nil)
+}
+
+fn io_error(action: &str, path: &str, err: io::Error) -> Error {
+    Error::IoError(format!("{} {}: {}", action, path, err))
+}
+
+// (type-of value)
+// 返回数据类型的名称，如 :number、:string、:function 等
+fn builtin_fn_type_of(objs: &[Object]) -> Result<Object, Error> {
+    if objs.len() != 1 {
+        return Err(Error::arity_error("1", objs.len()));
+    }
+
+    Ok(Object::Keyword(Keyword::intern(objs[0].type_name())))
+}
+
+// (arity function)
+// 返回用户自定义函数或者匿名函数的参数个数，
+// 如果参数个数可变，则返回形如 {:min 1 :max 3} 的映射表，不限个数时 :max 为 nil；
+// 如果函数有多个分支，则返回由各个分支的参数个数组成的向量；
+// 内置函数的参数个数由其自身检查，所以返回 nil
+fn builtin_fn_arity(objs: &[Object]) -> Result<Object, Error> {
+    if objs.len() != 1 {
+        return Err(Error::arity_error("1", objs.len()));
+    }
+
+    let clauses = match &objs[0] {
+        Object::Function(f) => match f.as_ref() {
+            Func::Builtin(..) => return Ok(Object::Nil),
+            Func::UserDefined(_, clauses, _) => clauses.as_slice(),
+            Func::Closure(clauses, _) => clauses.as_slice(),
+        },
+        obj => return Err(Error::type_error("function", obj).at_argument(1)),
+    };
+
+    let arity_of = |params: &Params| {
+        let min = params.min_arity();
+        match params.max_arity() {
+            Some(max) if max == min => Object::Number(min as i64),
+            max => Object::Map(
+                vec![
+                    (Object::Keyword(Keyword::intern("min")), Object::Number(min as i64)),
+                    (
+                        Object::Keyword(Keyword::intern("max")),
+                        max.map_or(Object::Nil, |m| Object::Number(m as i64)),
+                    ),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+        }
+    };
+
+    match clauses {
+        [clause] => Ok(arity_of(&clause.params)),
+        _ => Ok(Object::Vector(
+            clauses.iter().map(|clause| arity_of(&clause.params)).collect(),
+        )),
+    }
+}
+
+// (throw value)
+// 抛出一个错误，value 可以是任意数据
+fn builtin_fn_throw(objs: &[Object]) -> Result<Object, Error> {
+    if objs.len() != 1 {
+        return Err(Error::arity_error("1", objs.len()));
+    }
+
+    Err(Error::UserError(objs[0].clone()))
+}
+
+// (exit)
+// (exit status)
+// 结束程序的运行，status 为进程的退出状态（0 到 255），默认为 0。
+// exit 以一种不能被 try 表达式捕获的错误的形式向外传播，由程序的调用者（脚本运行器或者 REPL）处理
+fn builtin_fn_exit(objs: &[Object]) -> Result<Object, Error> {
+    if objs.len() > 1 {
+        return Err(Error::arity_error("0 or 1", objs.len()));
+    }
+
+    let status = match objs.first() {
+        Some(_) => arg(objs, 0, parse_number)?,
+        None => 0,
+    };
+    if !(0..=255).contains(&status) {
+        return Err(Error::ValueError(format!(
+            "exit status should be between 0 and 255, got {}",
+            status
+        )));
+    }
+
+    Err(Error::Exit(status as i32))
+}
+
+// 解析第 index 个（从 0 开始）参数，当类型不符时在错误信息里注明参数的位置（从 1 开始）
+fn arg<'a, T>(
+    objs: &'a [Object],
+    index: usize,
+    parse: impl FnOnce(&'a Object) -> Result<T, Error>,
+) -> Result<T, Error> {
+    parse(&objs[index]).map_err(|e| e.at_argument(index + 1))
+}
+
 fn parse_number_pair(objs: &[Object]) -> Result<(i64, i64), Error> {
     if objs.len() != 2 {
-        return Err(Error::EvalError("required 2 arguments".to_string()));
+        return Err(Error::arity_error("2", objs.len()));
     }
 
-    let left = parse_number(&objs[0])?;
-    let right = parse_number(&objs[1])?;
+    let left = arg(objs, 0, parse_number)?;
+    let right = arg(objs, 1, parse_number)?;
     Ok((left, right))
 }
 
 fn parse_number(obj: &Object) -> Result<i64, Error> {
     match obj {
         Object::Number(i) => Ok(*i),
-        _ => Err(Error::EvalError("the object is not a number".to_string())),
+        _ => Err(Error::type_error("number", obj)),
+    }
+}
+
+// 检查索引值是否在 [0, length) 范围之内，
+// 当 allow_end 为 true 时，允许索引值等于 length
+fn parse_index(obj: &Object, length: usize, allow_end: bool) -> Result<usize, Error> {
+    let i = parse_number(obj)?;
+    if i < 0 || i as usize > length || (i as usize == length && !allow_end) {
+        return Err(Error::IndexOutOfRange { index: i, length });
+    }
+
+    Ok(i as usize)
+}
+
+fn parse_vector(obj: &Object) -> Result<&Vector<Object>, Error> {
+    match obj {
+        Object::Vector(v) => Ok(v),
+        _ => Err(Error::type_error("vector", obj)),
+    }
+}
+
+fn parse_map(obj: &Object) -> Result<&OrdMap<Object, Object>, Error> {
+    match obj {
+        Object::Map(m) => Ok(m),
+        _ => Err(Error::type_error("map", obj)),
+    }
+}
+
+fn parse_set(obj: &Object) -> Result<&OrdSet<Object>, Error> {
+    match obj {
+        Object::Set(s) => Ok(s),
+        _ => Err(Error::type_error("set", obj)),
+    }
+}
+
+fn parse_single_char(objs: &[Object]) -> Result<char, Error> {
+    if objs.len() != 1 {
+        return Err(Error::arity_error("1", objs.len()));
+    }
+
+    arg(objs, 0, parse_char)
+}
+
+fn parse_char(obj: &Object) -> Result<char, Error> {
+    match obj {
+        Object::Char(c) => Ok(*c),
+        _ => Err(Error::type_error("char", obj)),
+    }
+}
+
+fn parse_string(obj: &Object) -> Result<&str, Error> {
+    match obj {
+        Object::String(s) => Ok(s),
+        _ => Err(Error::type_error("string", obj)),
     }
 }
 
 fn parse_bool_pair(objs: &[Object]) -> Result<(bool, bool), Error> {
     if objs.len() != 2 {
-        return Err(Error::EvalError("required 2 arguments".to_string()));
+        return Err(Error::arity_error("2", objs.len()));
     }
 
-    let left = parse_bool(&objs[0])?;
-    let right = parse_bool(&objs[1])?;
+    let left = arg(objs, 0, parse_bool)?;
+    let right = arg(objs, 1, parse_bool)?;
     Ok((left, right))
 }
 
 fn parse_bool(obj: &Object) -> Result<bool, Error> {
     match obj {
         Object::Bool(b) => Ok(*b),
-        _ => Err(Error::EvalError("the object is not a boolean".to_string())),
+        _ => Err(Error::type_error("bool", obj)),
     }
 }
 
 #[cfg(test)]
+#[allow(
+    clippy::assertions_on_constants,
+    clippy::collapsible_match,
+    clippy::redundant_pattern_matching
+)]
 mod tests {
     use super::Environment;
     use crate::{
@@ -266,7 +1098,7 @@ mod tests {
         match v1 {
             Some(f) => match f {
                 Object::Function(ff) => {
-                    assert!(matches!(ff.as_ref(), Func::Builtin(_)))
+                    assert!(matches!(ff.as_ref(), Func::Builtin("add", _)))
                 }
                 _ => assert!(false),
             },
@@ -1,6 +1,322 @@
+use core::fmt;
+
+use crate::ast::{Keyword, Object};
+
 #[derive(Debug)]
-pub enum Error{
-    EvalError(String)
+pub enum Error {
+    // 分词或者解析时的错误，比如括号不匹配、无效的字面量等
+    ParseError(String),
+
+    // 特殊形式（如 do, let, if, defn, fn 等）的格式错误
+    SyntaxError(String),
+
+    // 标识符未定义，suggestions 为名称相近的已定义的标识符
+    UnboundIdentifier { name: String, suggestions: Vec<String> },
+
+    // 标识符在当前作用域已经定义
+    AlreadyDefined(String),
+
+    // 数据类型不符，expected 为预期的类型，found 为实际的类型，value 为实际的值，
+    // function 为内置函数的名称，position 为参数的位置（从 1 开始）
+    TypeError {
+        function: Option<String>,
+        position: Option<usize>,
+        expected: String,
+        found: String,
+        value: String,
+    },
+
+    // 参数个数不符，name 为函数名称（匿名函数则为其源码），
+    // expected 为预期的参数个数，比如 "2"、"2 or 3"、"at least 1" 等
+    ArityError {
+        name: Option<String>,
+        expected: String,
+        got: usize,
+    },
+
+    // 算术运算错误，比如除以 0、整数溢出等
+    ArithmeticError(String),
+
+    // 索引超出范围
+    IndexOutOfRange { index: i64, length: usize },
+
+    // 数据类型正确但值无效，比如无效的 Unicode 码点
+    ValueError(String),
+
+    // 数据的形状跟模式不符，比如解构时列表的元素个数不符
+    MatchError(String),
+
+    // 文件读写错误，比如文件不存在
+    IoError(String),
+
+    // 其他运行时错误
+    RuntimeError(String),
+
+    // 求值被用户中断（在 REPL 里按 Ctrl-C）
+    Interrupted,
+
+    // 用户程序调用 exit 函数结束运行，值为进程的退出状态
+    Exit(i32),
+
+    // 由用户程序抛出的错误
+    UserError(Object),
+
+    // 带调用栈的错误
+    // 当错误穿过函数调用向外传播时，由 eval_function_call 把函数名称依次追加到 frames，
+    // 所以 frames 按从最内层到最外层的顺序排列。
+    StackTrace { error: Box<Error>, frames: Vec<String> },
+}
+
+impl Error {
+    // 追加一层调用栈
+    pub fn with_frame(self, frame: String) -> Error {
+        match self {
+            Error::StackTrace { error, mut frames } => {
+                frames.push(frame);
+                Error::StackTrace { error, frames }
+            }
+            _ => Error::StackTrace {
+                error: Box::new(self),
+                frames: vec![frame],
+            },
+        }
+    }
+
+    // 去除调用栈之后的原始错误
+    pub fn root(&self) -> &Error {
+        match self {
+            Error::StackTrace { error, .. } => error.root(),
+            _ => self,
+        }
+    }
+
+    // 如果是用户程序调用 exit 函数产生的“错误”，则返回其退出状态
+    pub fn exit_status(&self) -> Option<i32> {
+        match self.root() {
+            Error::Exit(status) => Some(*status),
+            _ => None,
+        }
+    }
+
+    pub fn into_root(self) -> Error {
+        match self {
+            Error::StackTrace { error, .. } => error.into_root(),
+            _ => self,
+        }
+    }
+
+    // 调用栈，按从最外层到最内层的顺序排列
+    pub fn stack_trace(&self) -> Vec<&str> {
+        match self {
+            Error::StackTrace { frames, .. } => frames.iter().rev().map(|f| f.as_str()).collect(),
+            _ => vec![],
+        }
+    }
+
+    pub fn type_error(expected: &str, found: &Object) -> Error {
+        Error::TypeError {
+            function: None,
+            position: None,
+            expected: expected.to_string(),
+            found: found.type_name().to_string(),
+            value: abbreviate(found.to_readable_string()),
+        }
+    }
+
+    // 为类型错误注明参数的位置
+    pub fn at_argument(self, position: usize) -> Error {
+        match self {
+            Error::TypeError {
+                function,
+                expected,
+                found,
+                value,
+                ..
+            } => Error::TypeError {
+                function,
+                position: Some(position),
+                expected,
+                found,
+                value,
+            },
+            _ => self,
+        }
+    }
+
+    // 为内置函数的类型错误和参数个数错误注明函数名称，
+    // 已经注明了函数名称的错误以及带调用栈的错误（即来自更内层的函数调用的错误）保持不变
+    pub fn in_function(self, name: &str) -> Error {
+        match self {
+            Error::TypeError {
+                function: None,
+                position,
+                expected,
+                found,
+                value,
+            } => Error::TypeError {
+                function: Some(name.to_string()),
+                position,
+                expected,
+                found,
+                value,
+            },
+            Error::ArityError {
+                name: None,
+                expected,
+                got,
+            } => Error::ArityError {
+                name: Some(name.to_string()),
+                expected,
+                got,
+            },
+            _ => self,
+        }
+    }
+
+    pub fn arity_error(expected: &str, got: usize) -> Error {
+        Error::ArityError {
+            name: None,
+            expected: expected.to_string(),
+            got,
+        }
+    }
+}
+
+// 避免在错误信息里输出过长的数据（比如数据的值、匿名函数的源码），
+// 超出长度的部分以 "..." 代替
+pub(crate) fn abbreviate(text: String) -> String {
+    const MAX_LENGTH: usize = 40;
+
+    if text.chars().count() > MAX_LENGTH {
+        text.chars().take(MAX_LENGTH).collect::<String>() + "..."
+    } else {
+        text
+    }
+}
+
+impl Error {
+    // 错误的种类，用于 try 表达式的 catch 子句匹配
+    //
+    // 由用户程序抛出的错误，如果其值为包含 `:kind` 关键字的映射表，则以该关键字为种类，
+    // 否则种类为 `user-error`。
+    pub fn kind(&self) -> Keyword {
+        let name = match self.root() {
+            Error::ParseError(_) => "parse-error",
+            Error::SyntaxError(_) => "syntax-error",
+            Error::UnboundIdentifier { .. } => "unbound-identifier",
+            Error::AlreadyDefined(_) => "already-defined",
+            Error::TypeError { .. } => "type-error",
+            Error::ArityError { .. } => "arity-error",
+            Error::ArithmeticError(_) => "arithmetic-error",
+            Error::IndexOutOfRange { .. } => "index-out-of-range",
+            Error::ValueError(_) => "value-error",
+            Error::MatchError(_) => "match-error",
+            Error::IoError(_) => "io-error",
+            Error::RuntimeError(_) => "runtime-error",
+            Error::Interrupted => "interrupted",
+            Error::Exit(_) => "exit",
+            Error::StackTrace { .. } => unreachable!(),
+            Error::UserError(obj) => {
+                if let Object::Map(map) = obj {
+                    if let Some(Object::Keyword(kind)) = map.get(&Object::Keyword(Keyword::intern("kind"))) {
+                        return *kind;
+                    }
+                }
+                "user-error"
+            }
+        };
+
+        Keyword::intern(name)
+    }
+
+    // 把错误转换为数据，以便在 catch 子句里绑定到标识符
+    //
+    // 由用户程序抛出的错误返回其原始的值，其他错误返回形如
+    // `{:kind :type-error :message "..."}` 的映射表。
+    pub fn to_object(&self) -> Object {
+        match self.root() {
+            Error::UserError(obj) => obj.clone(),
+            _ => Object::Map(
+                vec![
+                    (
+                        Object::Keyword(Keyword::intern("kind")),
+                        Object::Keyword(self.kind()),
+                    ),
+                    (
+                        Object::Keyword(Keyword::intern("message")),
+                        Object::String(self.root().to_string()),
+                    ),
+                ]
+                .into_iter()
+                .collect(),
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::ParseError(msg) => write!(formatter, "parse error: {}", msg),
+            Error::SyntaxError(msg) => write!(formatter, "syntax error: {}", msg),
+            Error::UnboundIdentifier { name, suggestions } => {
+                if suggestions.is_empty() {
+                    write!(formatter, "identifier not found: {}", name)
+                } else {
+                    write!(
+                        formatter,
+                        "identifier not found: {} (did you mean: {}?)",
+                        name,
+                        suggestions.join(", ")
+                    )
+                }
+            }
+            Error::AlreadyDefined(name) => write!(formatter, "identifier already exists: {}", name),
+            Error::TypeError {
+                function,
+                position,
+                expected,
+                found,
+                value,
+            } => {
+                match (function, position) {
+                    (Some(function), Some(position)) => {
+                        write!(formatter, "{}: argument {} ", function, position)?
+                    }
+                    (Some(function), None) => write!(formatter, "{}: ", function)?,
+                    (None, Some(position)) => write!(formatter, "type error: argument {} ", position)?,
+                    (None, None) => write!(formatter, "type error: ")?,
+                }
+                write!(formatter, "expected {}, got {} ({})", expected, found, value)
+            }
+            Error::ArityError {
+                name,
+                expected,
+                got,
+            } => {
+                let noun = if expected == "1" { "argument" } else { "arguments" };
+                match name {
+                    Some(name) => write!(formatter, "{}: ", name)?,
+                    None => write!(formatter, "arity error: ")?,
+                }
+                write!(formatter, "expected {} {}, got {}", expected, noun, got)
+            }
+            Error::ArithmeticError(msg) => write!(formatter, "arithmetic error: {}", msg),
+            Error::IndexOutOfRange { index, length } => write!(
+                formatter,
+                "index out of range: {} (length {})",
+                index, length
+            ),
+            Error::ValueError(msg) => write!(formatter, "value error: {}", msg),
+            Error::MatchError(msg) => write!(formatter, "match error: {}", msg),
+            Error::IoError(msg) => write!(formatter, "io error: {}", msg),
+            Error::RuntimeError(msg) => write!(formatter, "runtime error: {}", msg),
+            Error::Interrupted => write!(formatter, "interrupted"),
+            Error::Exit(status) => write!(formatter, "exit with status {}", status),
+            Error::UserError(obj) => write!(formatter, "error: {}", obj.to_readable_string()),
+            Error::StackTrace { error, .. } => write!(formatter, "{}", error),
+        }
+    }
+}
 
-    // ... 其他类型的错误
-}
\ No newline at end of file
+impl std::error::Error for Error {}
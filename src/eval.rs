@@ -1,33 +1,139 @@
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::HashMap;
 use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-use crate::ast::{Func, Object};
-use crate::env::{rc_env_define, rc_env_lookup, Environment};
-use crate::error::Error;
+use im_rc::{OrdMap, OrdSet};
+
+use crate::ast::{clauses_to_string, Clause, Func, Keyword, Object, Params};
+use crate::env::{rc_env_define, rc_env_io_enabled, rc_env_lookup, rc_env_names, Environment};
+use crate::error::{abbreviate, Error};
 use crate::parser::parse;
-use crate::token::tokenize;
+use crate::pattern::{destructure, match_pattern_names, pattern_names};
+use crate::suggestion::suggest;
+use crate::token::{strip_shebang, tokenize};
+
+// 特殊形式（即不按函数调用的方式求值的表达式）的名称
+// 注：eval 和 load-file 虽然跟函数一样先对参数求值，但它们需要访问当前的环境，所以也作为特殊形式实现
+pub(crate) const SPECIAL_FORMS: &[&str] = &[
+    "do", "let", "let*", "if", "match", "defn", "fn", "try", "eval", "load-file",
+];
+
+// 中断标志，由 REPL 的 Ctrl-C 信号处理函数设置（信号处理函数运行在另外的线程，所以使用原子类型），
+// eval 每一步都检查这个标志，从而能够中断正在运行的求值（比如死循环）
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+// 请求中断正在运行的求值
+pub fn interrupt() {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+// 清除中断标志，在开始新的求值之前调用
+pub fn clear_interrupt() {
+    INTERRUPTED.store(false, Ordering::SeqCst);
+}
+
+thread_local! {
+    // eval 被调用的次数，即求值的步数，用于 REPL 的 :time 命令
+    static EVAL_STEPS: Cell<u64> = const { Cell::new(0) };
+}
+
+// 到目前为止求值的总步数
+pub fn eval_steps() -> u64 {
+    EVAL_STEPS.with(|steps| steps.get())
+}
 
 fn eval(node: &Object, rc_env: &Rc<RefCell<Option<Environment>>>) -> Result<Object, Error> {
+    EVAL_STEPS.with(|steps| steps.set(steps.get() + 1));
+
+    if INTERRUPTED.load(Ordering::SeqCst) {
+        return Err(Error::Interrupted);
+    }
+
     match node {
         // 标识符，从 Environment 里获取对应的值
         // 注：lookup 方法返回的是值的 clone
         Object::Symbol(name) => match rc_env_lookup(rc_env, name) {
             Some(obj) => Ok(obj),
-            None => Err(Error::EvalError(format!("identifier not found: {}", name))),
+            // 特殊形式不是值，比如不能 `(let f eval)`
+            None if SPECIAL_FORMS.contains(&name.as_str()) => Err(Error::SyntaxError(format!(
+                "{} is a special form and cannot be used as a value",
+                name
+            ))),
+            None => {
+                let names = rc_env_names(rc_env);
+                let candidates = names
+                    .iter()
+                    .map(|n| n.as_str())
+                    .chain(SPECIAL_FORMS.iter().copied());
+                Err(Error::UnboundIdentifier {
+                    name: name.clone(),
+                    suggestions: suggest(name, candidates),
+                })
+            }
         },
-        // 数字
-        Object::Number(_) => Ok(node.clone()),
-        // 布尔值
-        Object::Bool(_) => Ok(node.clone()),
+        // 数字、字符和字符串
+        Object::Number(_) | Object::Char(_) | Object::String(_) => Ok(node.clone()),
+        // nil 和布尔值
+        Object::Nil | Object::Bool(_) => Ok(node.clone()),
+        // 关键字
+        Object::Keyword(_) => Ok(node.clone()),
         // 列表
         Object::List(list) => {
             let (first_node, rest_nodes) = list
                 .split_first()
-                .ok_or(Error::EvalError("empty list".to_string()))?;
+                .ok_or(Error::SyntaxError("empty list".to_string()))?;
             eval_list(first_node, rest_nodes, rc_env)
         }
-        _ => Err(Error::EvalError("unsupported object".to_string())),
+        // 向量，对每个元素求值
+        Object::Vector(vector) => {
+            let items = vector
+                .iter()
+                .map(|n| eval(n, rc_env))
+                .collect::<Result<Vec<Object>, Error>>()?;
+            Ok(Object::Vector(items.into_iter().collect()))
+        }
+        // 映射表，按源码的顺序对每个键和值求值，
+        // 求值之后的键不能重复（比如 `{(add 1 1) 1 2 2}`）
+        Object::Map(map) => {
+            let keys: Vec<&Object> = match map.source_order() {
+                Some(order) => order.iter().collect(),
+                None => map.keys().collect(),
+            };
+
+            let mut entries = OrdMap::new();
+            for key_node in keys {
+                let key = eval(key_node, rc_env)?;
+                let value = eval(&map[key_node], rc_env)?;
+                if entries.insert(key.clone(), value).is_some() {
+                    return Err(Error::ValueError(format!(
+                        "duplicate key in map literal: {}",
+                        key.to_readable_string()
+                    )));
+                }
+            }
+            Ok(Object::Map(entries.into()))
+        }
+        // 集合，按源码的顺序对每个元素求值，求值之后的元素不能重复
+        Object::Set(set) => {
+            let nodes: Vec<&Object> = match set.source_order() {
+                Some(order) => order.iter().collect(),
+                None => set.iter().collect(),
+            };
+
+            let mut items = OrdSet::new();
+            for node in nodes {
+                let item = eval(node, rc_env)?;
+                if items.insert(item.clone()).is_some() {
+                    return Err(Error::ValueError(format!(
+                        "duplicate item in set literal: {}",
+                        item.to_readable_string()
+                    )));
+                }
+            }
+            Ok(Object::Set(items.into()))
+        }
+        _ => Err(Error::RuntimeError("unsupported object".to_string())),
     }
 }
 
@@ -42,67 +148,108 @@ fn eval_list(
             match name.as_str() {
                 "do" => eval_do(rest_nodes, rc_env),
                 "let" => eval_let(rest_nodes, rc_env),
+                "let*" => eval_let_star(rest_nodes, rc_env),
                 "if" => eval_if(rest_nodes, rc_env),
+                "match" => eval_match(rest_nodes, rc_env),
                 "defn" => eval_defn(rest_nodes, rc_env),
                 "fn" => eval_fn(rest_nodes, rc_env),
+                "try" => eval_try(rest_nodes, rc_env),
+                "eval" => eval_eval(rest_nodes, rc_env),
+                "load-file" => eval_load_file(rest_nodes, rc_env),
                 _ => {
                     // 预期是函数（内置函数、用户自定义函数或者匿名函数）
                     eval_function_call(node, rest_nodes, rc_env)
                 }
             }
         }
-        _ => Err(Error::EvalError(
+        // 关键字可以当作函数使用，用于从映射表获取值，如 (:name person)
+        Object::Keyword(_) => eval_function_call(node, rest_nodes, rc_env),
+        _ => Err(Error::SyntaxError(
             "the first element of the list should be a symbol".to_string(),
         )),
     }
 }
 
+// 依次执行一组表达式，返回最后一个表达式的值，
+// 空的 (do) 返回 nil
 fn eval_do(nodes: &[Object], rc_env: &Rc<RefCell<Option<Environment>>>) -> Result<Object, Error> {
-    if nodes.len() == 0 {
-        return Err(Error::EvalError(
-            "sub-expressions are required in DO expression".to_string(),
-        ));
-    }
-
     let child_env = Environment::new(rc_env);
     let rc_child_env = child_env.to_rc_env();
 
-    let mut result = Err(Error::EvalError("unreachable".to_string()));
+    let mut result = Object::Nil;
 
     for node in nodes {
-        result = eval(node, &rc_child_env);
+        result = eval(node, &rc_child_env)?;
     }
 
-    result
+    Ok(result)
 }
 
 fn eval_let(nodes: &[Object], rc_env: &Rc<RefCell<Option<Environment>>>) -> Result<Object, Error> {
+    // e.g.
+    // (let name value)
+    // (let (a b) value)
     if nodes.len() != 2 {
-        return Err(Error::EvalError(
+        return Err(Error::SyntaxError(
             "expected 2 sub-expressions for the LET expression".to_string(),
         ));
     }
 
-    let name_object = &nodes[0];
+    let pattern = &nodes[0];
 
-    match name_object {
-        Object::Symbol(name) => {
+    match pattern {
+        Object::Symbol(_) | Object::List(_) | Object::Vector(_) | Object::Map(_) => {
+            pattern_names(pattern)?;
             let value_object = eval(&nodes[1], rc_env)?;
-            rc_env_define(rc_env, name, value_object.clone())?;
+            bind_pattern(pattern, &value_object, rc_env)?;
             Ok(value_object)
         }
-        _ => Err(Error::EvalError(
+        _ => Err(Error::SyntaxError(
             "the identifier should be a string/symbol".to_string(),
         )),
     }
 }
 
+fn eval_let_star(nodes: &[Object], rc_env: &Rc<RefCell<Option<Environment>>>) -> Result<Object, Error> {
+    // e.g. (let* ((name1 value1) ((a b) value2)) body1 body2 ...)
+    // 每个绑定都位于一个新的作用域，其父作用域是前一个绑定的作用域，
+    // 所以后面的值可以引用前面绑定的标识符，也可以遮盖前面绑定的同名标识符，
+    // 绑定完毕之后依次执行 body，返回最后一个表达式的值
+    let (bindings, body) = match nodes.split_first() {
+        Some((Object::List(bindings), body)) => (bindings, body),
+        _ => {
+            return Err(Error::SyntaxError(
+                "expected a binding list for the LET* expression".to_string(),
+            ))
+        }
+    };
+
+    let mut rc_child_env = Rc::clone(rc_env);
+
+    for binding in bindings {
+        match binding {
+            Object::List(pair) if pair.len() == 2 => {
+                rc_child_env = Environment::new(&rc_child_env).to_rc_env();
+                eval_let(pair, &rc_child_env)?;
+            }
+            _ => {
+                return Err(Error::SyntaxError(
+                    "expected (pattern value) for each binding of the LET* expression".to_string(),
+                ))
+            }
+        }
+    }
+
+    eval_do(body, &rc_child_env)
+}
+
 fn eval_if(nodes: &[Object], rc_env: &Rc<RefCell<Option<Environment>>>) -> Result<Object, Error> {
     // e.g. (if test sequence alternative)
+    // 省略 alternative 时，如果 test 的值为 false，则返回 nil
 
-    if nodes.len() != 3 {
-        return Err(Error::EvalError(
-            "expected 3 sub-expressions for the IF expression".to_string(),
+    if nodes.len() != 2 && nodes.len() != 3 {
+        return Err(Error::SyntaxError(
+            "expected 2 or 3 sub-expressions for the IF expression".to_string(),
         ));
     }
 
@@ -112,57 +259,405 @@ fn eval_if(nodes: &[Object], rc_env: &Rc<RefCell<Option<Environment>>>) -> Resul
             if b {
                 eval(&nodes[1], rc_env)
             } else {
-                eval(&nodes[2], rc_env)
+                match nodes.get(2) {
+                    Some(alternative) => eval(alternative, rc_env),
+                    None => Ok(Object::Nil),
+                }
             }
         }
-        _ => Err(Error::EvalError(
-            "expected a bool value for the IF test expression".to_string(),
-        )),
+        _ => Err(Error::type_error("bool", &test_object)),
     }
 }
 
-fn eval_defn(nodes: &[Object], rc_env: &Rc<RefCell<Option<Environment>>>) -> Result<Object, Error> {
-    // e.g. (defn name (param1 param2) body)
-    if nodes.len() != 3 {
-        return Err(Error::EvalError(
-            "expected 3 sub-expressions for the DEFN expression".to_string(),
-        ));
+// match 表达式的分支
+struct MatchClause<'a> {
+    pattern: &'a Object,
+    guard: Option<&'a Object>, // `:when` 后面的守卫表达式
+    body: &'a Object,
+}
+
+fn eval_match(nodes: &[Object], rc_env: &Rc<RefCell<Option<Environment>>>) -> Result<Object, Error> {
+    // e.g.
+    // (match value
+    //     0 :zero
+    //     (:add a b) (add a b)
+    //     [x &rest _] :when (gt x 0) x
+    //     {:keys (name)} name
+    //     _ :other)
+    //
+    // 按顺序尝试各个分支的模式，模式的格式详见 pattern 模块。
+    // 只有模式匹配且守卫表达式（如果有的话）的值为 true 时才执行该分支，
+    // 守卫表达式和分支的表达式都可以引用模式所绑定的标识符；
+    // 如果所有分支都不匹配，则返回 :match-error 错误。
+
+    let (value_node, mut remain) = nodes.split_first().ok_or(Error::SyntaxError(
+        "expected a value for the MATCH expression".to_string(),
+    ))?;
+
+    let mut clauses: Vec<MatchClause> = vec![];
+    while let Some((pattern, rest)) = remain.split_first() {
+        match_pattern_names(pattern)?;
+
+        let (guard, rest) = match rest {
+            [Object::Keyword(k), guard, rest @ ..] if *k == Keyword::intern("when") => (Some(guard), rest),
+            _ => (None, rest),
+        };
+
+        let (body, rest) = rest.split_first().ok_or(Error::SyntaxError(
+            "expected an expression after the pattern of the MATCH expression".to_string(),
+        ))?;
+
+        clauses.push(MatchClause {
+            pattern,
+            guard,
+            body,
+        });
+        remain = rest;
     }
 
-    let r_name = match &nodes[0] {
-        Object::Symbol(name) => Ok(name),
-        _ => Err(Error::EvalError(
-            "function name should be a symbol".to_string(),
-        )),
-    }?;
+    let value = eval(value_node, rc_env)?;
 
-    let params = match &nodes[1] {
-        Object::List(list) => {
-            let symbol_list: Vec<String> = list
-                .iter()
-                .filter_map(|x| match x {
-                    Object::Symbol(s) => Some(s.clone()),
-                    _ => None,
+    for clause in &clauses {
+        // 解构失败即表示模式不匹配
+        let mut bindings = vec![];
+        if destructure(clause.pattern, &value, &mut bindings).is_err() {
+            continue;
+        }
+
+        let clause_env = Environment::new_with_records(bindings.into_iter().collect(), rc_env);
+        let rc_clause_env = clause_env.to_rc_env();
+
+        if let Some(guard) = clause.guard {
+            match eval(guard, &rc_clause_env)? {
+                Object::Bool(true) => {}
+                Object::Bool(false) => continue,
+                obj => return Err(Error::type_error("bool", &obj)),
+            }
+        }
+
+        return eval(clause.body, &rc_clause_env);
+    }
+
+    Err(Error::MatchError(format!(
+        "non-exhaustive match: no pattern matches {}",
+        abbreviate(value.to_readable_string())
+    )))
+}
+
+// try 表达式里的 catch 子句
+struct CatchClause<'a> {
+    kind: Option<Keyword>, // None 表示捕获任意种类的错误
+    name: &'a str,
+    body: &'a [Object],
+}
+
+fn eval_try(nodes: &[Object], rc_env: &Rc<RefCell<Option<Environment>>>) -> Result<Object, Error> {
+    // e.g.
+    // (try
+    //     body1 body2 ...
+    //     (catch :type-error e handler1 handler2 ...)
+    //     (catch _ e handler1 handler2 ...)
+    //     (finally cleanup1 cleanup2 ...))
+    //
+    // catch 子句按顺序匹配错误的种类，`_` 表示匹配任意种类的错误；
+    // finally 子句无论是否发生错误都会执行，其值被忽略，
+    // 但如果 finally 子句本身发生错误，则该错误取代原来的结果。
+
+    let is_clause = |node: &Object, clause_name: &str| match node {
+        Object::List(list) => matches!(list.first(), Some(Object::Symbol(s)) if s == clause_name),
+        _ => false,
+    };
+
+    let body_length = nodes
+        .iter()
+        .position(|n| is_clause(n, "catch") || is_clause(n, "finally"))
+        .unwrap_or(nodes.len());
+    let (body, mut clauses) = nodes.split_at(body_length);
+
+    let mut finally_body: Option<&[Object]> = None;
+    if let Some((last, rest)) = clauses.split_last() {
+        if is_clause(last, "finally") {
+            if let Object::List(list) = last {
+                finally_body = Some(&list[1..]);
+            }
+            clauses = rest;
+        }
+    }
+
+    let catch_clauses = clauses
+        .iter()
+        .map(|clause| match clause {
+            Object::List(list) if is_clause(clause, "catch") && list.len() >= 3 => {
+                let kind = match &list[1] {
+                    Object::Keyword(k) => Some(*k),
+                    Object::Symbol(s) if s == "_" => None,
+                    _ => {
+                        return Err(Error::SyntaxError(
+                            "the error kind of CATCH clause should be a keyword or `_`".to_string(),
+                        ))
+                    }
+                };
+                let name = match &list[2] {
+                    Object::Symbol(s) => s.as_str(),
+                    _ => {
+                        return Err(Error::SyntaxError(
+                            "the identifier of CATCH clause should be a symbol".to_string(),
+                        ))
+                    }
+                };
+                Ok(CatchClause {
+                    kind,
+                    name,
+                    body: &list[3..],
                 })
-                .collect();
+            }
+            _ => Err(Error::SyntaxError(
+                "expected (catch kind identifier body...) or a trailing (finally body...) in TRY expression"
+                    .to_string(),
+            )),
+        })
+        .collect::<Result<Vec<CatchClause>, Error>>()?;
+
+    let result = match eval_do(body, rc_env) {
+        // 中断不能被捕获，否则无法中断 try 表达式里的死循环；exit 也不能被捕获
+        Err(err) if matches!(err.root(), Error::Interrupted | Error::Exit(_)) => Err(err),
+        Err(err) => {
+            let kind = err.kind();
+            match catch_clauses
+                .iter()
+                .find(|c| c.kind.is_none() || c.kind == Some(kind))
+            {
+                Some(clause) => {
+                    let mut records = HashMap::<String, Object>::new();
+                    records.insert(clause.name.to_string(), err.to_object());
+                    let catch_env = Environment::new_with_records(records, rc_env);
+                    eval_do(clause.body, &catch_env.to_rc_env())
+                }
+                None => Err(err),
+            }
+        }
+        ok => ok,
+    };
+
+    if let Some(cleanup) = finally_body {
+        eval_do(cleanup, rc_env)?;
+    }
+
+    result
+}
+
+// 解析函数的参数列表
+// e.g. (a b &optional (c 10) d &rest others)
+//      (a b &rest others &key (size 1) color)
+//
+// `&optional`、`&rest` 和 `&key` 必须按此顺序出现且最多出现一次，
+// `&rest` 后面有且只有一个参数，参数名称不能重复。
+// `&optional` 和 `&key` 不能同时使用，因为可选参数会先消耗掉 `:name value` 形式的实参，
+// 比如 (f 1 :size 3) 的 `:size` 和 `3` 会被当作可选参数的值。
+fn parse_params(node: &Object) -> Result<Params, Error> {
+    #[derive(PartialEq, PartialOrd, Clone, Copy)]
+    enum Section {
+        Required,
+        Optional,
+        Rest,
+        Key,
+    }
 
-            if symbol_list.len() != list.len() {
-                return Err(Error::EvalError(
+    let list = match node {
+        Object::List(list) => list,
+        _ => return Err(Error::SyntaxError("expected parameter name list".to_string())),
+    };
+
+    let mut params = Params::default();
+    let mut section = Section::Required;
+    let mut names: Vec<String> = vec![];
+
+    for item in list {
+        // 参数的名称或者解构的模式，以及可选参数和关键字参数的默认值
+        let (pattern, default) = match item {
+            Object::Symbol(s) if s.starts_with('&') => {
+                let next_section = match s.as_str() {
+                    "&optional" => Section::Optional,
+                    "&rest" => Section::Rest,
+                    "&key" => Section::Key,
+                    _ => return Err(Error::SyntaxError(format!("unknown parameter marker: {}", s))),
+                };
+
+                if next_section <= section {
+                    return Err(Error::SyntaxError(format!("unexpected {} in parameter list", s)));
+                }
+                let has_optional = section == Section::Optional || !params.optional.is_empty();
+                if next_section == Section::Key && has_optional {
+                    return Err(Error::SyntaxError(
+                        "&optional and &key cannot be used in the same parameter list".to_string(),
+                    ));
+                }
+                if section == Section::Rest && params.rest.is_none() {
+                    return Err(Error::SyntaxError(
+                        "expected a parameter name after &rest".to_string(),
+                    ));
+                }
+
+                section = next_section;
+                continue;
+            }
+            Object::Symbol(_) => (item.clone(), Object::Nil),
+            Object::List(pair) if matches!(section, Section::Optional | Section::Key) => {
+                match pair.as_slice() {
+                    [Object::Symbol(s), _] if s.starts_with('&') => {
+                        return Err(Error::SyntaxError(format!("unexpected {} in parameter list", s)))
+                    }
+                    [Object::Symbol(_), default] => (pair[0].clone(), default.clone()),
+                    [pattern, default] if section == Section::Optional => {
+                        (pattern.clone(), default.clone())
+                    }
+                    _ => {
+                        return Err(Error::SyntaxError(
+                            "expected (name default) for parameter with default value".to_string(),
+                        ))
+                    }
+                }
+            }
+            Object::List(_) | Object::Vector(_) | Object::Map(_) if section == Section::Required => {
+                (item.clone(), Object::Nil)
+            }
+            _ => {
+                return Err(Error::SyntaxError(
                     "parameter name should be a string/symbol".to_string(),
-                ));
-            } else {
-                Ok(symbol_list)
+                ))
+            }
+        };
+
+        for name in pattern_names(&pattern)? {
+            if names.contains(&name) {
+                return Err(Error::SyntaxError(format!("duplicate parameter name: {}", name)));
+            }
+            names.push(name);
+        }
+
+        match section {
+            Section::Required => params.required.push(pattern),
+            Section::Optional => params.optional.push((pattern, default)),
+            Section::Rest if params.rest.is_none() => params.rest = Some(pattern.to_string()),
+            Section::Rest => {
+                return Err(Error::SyntaxError(
+                    "expected only one parameter name after &rest".to_string(),
+                ))
+            }
+            Section::Key => params.keys.push((pattern.to_string(), default)),
+        }
+    }
+
+    let empty_section = match section {
+        Section::Optional => params.optional.is_empty(),
+        Section::Rest => params.rest.is_none(),
+        Section::Key => params.keys.is_empty(),
+        Section::Required => false,
+    };
+    if empty_section {
+        return Err(Error::SyntaxError(
+            "expected a parameter name after the parameter marker".to_string(),
+        ));
+    }
+
+    Ok(params)
+}
+
+// 解析函数的分支，nodes 为 defn 的函数名称之后或者 fn 之后的部分，form 为用于错误信息的表达式名称
+// e.g.
+// 单个分支：(param1 param2) body
+// 多个分支：((param1) body1) ((param1 param2) body2) ...
+//
+// 多个分支所接受的实参个数不能重叠，比如不能同时有 (a b) 和 (a &rest xs) 两个分支。
+fn parse_clauses(nodes: &[Object], form: &str) -> Result<Vec<Clause>, Error> {
+    // 每个分支都是以参数列表开头的列表。
+    // 注：单个分支的函数体不可能是以列表开头的列表（列表的第一个元素必须是标识符），
+    // 所以两种形式不会混淆。
+    let is_clause = |node: &Object| matches!(node, Object::List(list) if matches!(list.first(), Some(Object::List(_))));
+
+    if nodes.is_empty() || !nodes.iter().all(is_clause) {
+        if nodes.len() != 2 {
+            return Err(Error::SyntaxError(format!(
+                "expected a parameter list and a body for the {} expression",
+                form
+            )));
+        }
+
+        return Ok(vec![Clause {
+            params: parse_params(&nodes[0])?,
+            body: nodes[1].clone(),
+        }]);
+    }
+
+    let mut clauses: Vec<Clause> = vec![];
+    for node in nodes {
+        let clause = match node {
+            Object::List(list) if list.len() == 2 => Clause {
+                params: parse_params(&list[0])?,
+                body: list[1].clone(),
+            },
+            _ => {
+                return Err(Error::SyntaxError(format!(
+                    "expected (params body) for each clause of the {} expression",
+                    form
+                )))
+            }
+        };
+
+        // 检查是否跟前面的分支重叠，即存在两个分支都接受的实参个数
+        for previous in &clauses {
+            let (left, right) = (&previous.params, &clause.params);
+            let lower = left.min_arity().max(right.min_arity());
+            if left.accepts(lower) && right.accepts(lower) {
+                return Err(Error::SyntaxError(format!(
+                    "duplicate arity {} in the clauses of the {} expression",
+                    lower, form
+                )));
             }
         }
-        _ => Err(Error::EvalError("expected parameter name list".to_string())),
+
+        clauses.push(clause);
+    }
+
+    Ok(clauses)
+}
+
+// 根据实参的个数选择函数的分支，name 生成用于错误信息的函数名称（只在出错时调用）
+fn select_clause<'a>(
+    name: &dyn Fn() -> String,
+    clauses: &'a [Clause],
+    count: usize,
+) -> Result<&'a Clause, Error> {
+    clauses
+        .iter()
+        .find(|clause| clause.params.accepts(count))
+        .ok_or_else(|| Error::ArityError {
+            name: Some(name()),
+            expected: clauses
+                .iter()
+                .map(|clause| clause.params.arity_description())
+                .collect::<Vec<String>>()
+                .join(" or "),
+            got: count,
+        })
+}
+
+fn eval_defn(nodes: &[Object], rc_env: &Rc<RefCell<Option<Environment>>>) -> Result<Object, Error> {
+    // e.g.
+    // (defn name (param1 param2) body)
+    // (defn name ((param1) body1) ((param1 param2) body2))
+    let r_name = match nodes.first() {
+        Some(Object::Symbol(name)) => Ok(name),
+        _ => Err(Error::SyntaxError(
+            "function name should be a symbol".to_string(),
+        )),
     }?;
 
-    let body = (&nodes[2]).clone();
+    let clauses = parse_clauses(&nodes[1..], "DEFN")?;
 
     let defn = Object::Function(Box::new(Func::UserDefined(
         r_name.clone(),
-        params,
-        body,
+        clauses,
         Rc::downgrade(rc_env),
     )));
 
@@ -171,37 +666,12 @@ fn eval_defn(nodes: &[Object], rc_env: &Rc<RefCell<Option<Environment>>>) -> Res
 }
 
 fn eval_fn(nodes: &[Object], rc_env: &Rc<RefCell<Option<Environment>>>) -> Result<Object, Error> {
-    // e.g. (fn (param1 param2) body)
-    if nodes.len() != 2 {
-        return Err(Error::EvalError(
-            "expected 2 sub-expressions for the FN expression".to_string(),
-        ));
-    }
-
-    let params = match &nodes[0] {
-        Object::List(list) => {
-            let symbol_list: Vec<String> = list
-                .iter()
-                .filter_map(|x| match x {
-                    Object::Symbol(s) => Some(s.clone()),
-                    _ => None,
-                })
-                .collect();
-
-            if symbol_list.len() != list.len() {
-                return Err(Error::EvalError(
-                    "parameter name should be a string/symbol".to_string(),
-                ));
-            } else {
-                Ok(symbol_list)
-            }
-        }
-        _ => Err(Error::EvalError("expected parameter name list".to_string())),
-    }?;
+    // e.g.
+    // (fn (param1 param2) body)
+    // (fn ((param1) body1) ((param1 param2) body2))
+    let clauses = parse_clauses(nodes, "FN")?;
 
-    let body = (&nodes[1]).clone();
-
-    let defn = Object::Function(Box::new(Func::Closure(params, body, Rc::clone(rc_env))));
+    let defn = Object::Function(Box::new(Func::Closure(Rc::new(clauses), Rc::clone(rc_env))));
 
     Ok(defn)
 }
@@ -214,81 +684,316 @@ fn eval_function_call(
     let first_eval = eval(node, rc_env)?;
     match first_eval {
         Object::Function(f) => match f.as_ref() {
-            Func::Builtin(bf) => {
+            Func::Builtin(name, bf) => {
                 let args = rest_nodes
                     .iter()
                     .map(|n| eval(n, rc_env))
                     .collect::<Result<Vec<Object>, Error>>()?;
 
-                bf(&args)
+                bf(&args).map_err(|e| e.in_function(name).with_frame(name.to_string()))
             }
-            Func::UserDefined(_, params, body, static_scope_env) => {
+            Func::UserDefined(name, clauses, static_scope_env) => {
                 let args = rest_nodes
                     .iter()
                     .map(|n| eval(n, rc_env))
                     .collect::<Result<Vec<Object>, Error>>()?;
 
-                if args.len() != params.len() {
-                    return Err(Error::EvalError("args length error".to_string()));
-                }
-
-                // 填充实参
-                let mut records = HashMap::<String, Object>::new();
-                for (idx, name) in params.iter().enumerate() {
-                    records.insert(name.clone(), args[idx].clone());
-                }
-
                 let option_define_env = static_scope_env.upgrade();
                 match option_define_env {
                     Some(define_env) => {
-                        let activate_env = Environment::new_with_records(records, &define_env);
-                        let rc_activate_env = activate_env.to_rc_env();
-                        eval(body, &rc_activate_env)
+                        // 参数个数不符、解构失败以及默认值求值的错误也属于被调用的函数
+                        let rc_activate_env = Environment::new(&define_env).to_rc_env();
+                        let name_of = || name.clone();
+                        select_clause(&name_of, clauses, args.len())
+                            .and_then(|clause| {
+                                bind_params(&name_of, &clause.params, &args, &rc_activate_env)?;
+                                eval(&clause.body, &rc_activate_env)
+                            })
+                            .map_err(|e| e.with_frame(name.clone()))
                     }
-                    None => Err(Error::EvalError(
+                    None => Err(Error::RuntimeError(
                         "static scope environment not found.".to_string(),
                     )),
                 }
             }
-            Func::Closure(params, body, static_scope_env) => {
+            Func::Closure(clauses, static_scope_env) => {
                 let args = rest_nodes
                     .iter()
                     .map(|n| eval(n, rc_env))
                     .collect::<Result<Vec<Object>, Error>>()?;
 
-                if args.len() != params.len() {
-                    return Err(Error::EvalError("args length error".to_string()));
-                }
-
-                // 填充实参
-                let mut records = HashMap::<String, Object>::new();
-                for (idx, name) in params.iter().enumerate() {
-                    records.insert(name.clone(), args[idx].clone());
-                }
+                // 匿名函数没有名称，错误信息以及调用栈里以其（缩略的）源码代替，
+                // 使得嵌套的匿名函数在调用栈里也能互相区分。
+                // 源码只在出错时才生成，以免拖慢正常的调用
+                let source = || abbreviate(format!("(fn {})", clauses_to_string(clauses)));
 
                 // 注：这里跟 Func::UserDefined 的不同
-                let activate_env = Environment::new_with_records(records, static_scope_env);
-                let rc_activate_env = activate_env.to_rc_env();
-                eval(body, &rc_activate_env)
+                let rc_activate_env = Environment::new(static_scope_env).to_rc_env();
+                select_clause(&source, clauses, args.len())
+                    .and_then(|clause| {
+                        bind_params(&source, &clause.params, &args, &rc_activate_env)?;
+                        eval(&clause.body, &rc_activate_env)
+                    })
+                    .map_err(|e| e.with_frame(source()))
+            }
+        },
+        Object::Keyword(keyword) => {
+            let args = rest_nodes
+                .iter()
+                .map(|n| eval(n, rc_env))
+                .collect::<Result<Vec<Object>, Error>>()?;
+
+            eval_keyword_call(keyword, &args).map_err(|e| e.with_frame(format!(":{}", keyword.name())))
+        }
+        _ => Err(Error::type_error("function", &first_eval)),
+    }
+}
+
+// 把实参绑定到函数的作用域 rc_activate_env，name 为用于错误信息的函数名称，
+// 实参的个数已经由 select_clause 检查过
+//
+// 实参依次填充必选参数和可选参数，剩余的实参组成列表绑定到剩余参数，
+// 同时剩余的实参还按 `:name value` 的形式成对地填充关键字参数。
+// 可选参数和关键字参数的默认值表达式在函数的作用域里求值，所以可以引用前面的参数。
+fn bind_params(
+    name: &dyn Fn() -> String,
+    params: &Params,
+    args: &[Object],
+    rc_activate_env: &Rc<RefCell<Option<Environment>>>,
+) -> Result<(), Error> {
+    let (positional_args, rest_args) =
+        args.split_at(args.len().min(params.required.len() + params.optional.len()));
+
+    for (pattern, arg) in params.required.iter().zip(positional_args) {
+        bind_pattern(pattern, arg, rc_activate_env)?;
+    }
+
+    for (idx, (pattern, default)) in params.optional.iter().enumerate() {
+        let value = match positional_args.get(params.required.len() + idx) {
+            Some(arg) => arg.clone(),
+            None => eval(default, rc_activate_env)?,
+        };
+        bind_pattern(pattern, &value, rc_activate_env)?;
+    }
+
+    if let Some(param) = &params.rest {
+        rc_env_define(rc_activate_env, param, Object::List(rest_args.to_vec()))?;
+    }
+
+    if params.keys.is_empty() {
+        return Ok(());
+    }
+
+    let offset = args.len() - rest_args.len();
+    let mut key_args = HashMap::<String, Object>::new();
+    for (idx, pair) in rest_args.chunks(2).enumerate() {
+        let key = match &pair[0] {
+            Object::Keyword(k) => k.name().to_string(),
+            obj => {
+                return Err(Error::type_error("keyword", obj)
+                    .at_argument(offset + idx * 2 + 1)
+                    .in_function(&name()))
             }
+        };
+
+        if !params.keys.iter().any(|(param, _)| *param == key) {
+            return Err(Error::ValueError(format!(
+                "unknown keyword argument :{} for {}",
+                key,
+                name()
+            )));
+        }
+
+        let value = pair.get(1).ok_or_else(|| {
+            Error::ValueError(format!(
+                "missing value for keyword argument :{} for {}",
+                key,
+                name()
+            ))
+        })?;
+
+        if key_args.insert(key.clone(), value.clone()).is_some() {
+            return Err(Error::ValueError(format!(
+                "duplicate keyword argument :{} for {}",
+                key,
+                name()
+            )));
+        }
+    }
+
+    for (param, default) in &params.keys {
+        let value = match key_args.remove(param) {
+            Some(arg) => arg,
+            None => eval(default, rc_activate_env)?,
+        };
+        rc_env_define(rc_activate_env, param, value)?;
+    }
+
+    Ok(())
+}
+
+// 按模式解构数据，并把各个标识符绑定到作用域 rc_env
+// let、let* 以及函数的参数都使用这个方法绑定
+fn bind_pattern(
+    pattern: &Object,
+    value: &Object,
+    rc_env: &Rc<RefCell<Option<Environment>>>,
+) -> Result<(), Error> {
+    let mut bindings = vec![];
+    destructure(pattern, value, &mut bindings)?;
+
+    for (name, obj) in bindings {
+        rc_env_define(rc_env, &name, obj)?;
+    }
+
+    Ok(())
+}
+
+// (:key map)
+// (:key map default)
+// 从映射表获取关键字对应的值，当键不存在时，如果提供了 default 则返回 default，否则返回 nil
+fn eval_keyword_call(keyword: Keyword, args: &[Object]) -> Result<Object, Error> {
+    if args.len() != 1 && args.len() != 2 {
+        return Err(Error::ArityError {
+            name: Some(format!(":{}", keyword.name())),
+            expected: "1 or 2".to_string(),
+            got: args.len(),
+        });
+    }
+
+    let map = match &args[0] {
+        Object::Map(map) => map,
+        obj => {
+            return Err(Error::type_error("map", obj)
+                .at_argument(1)
+                .in_function(&format!(":{}", keyword.name())))
+        }
+    };
+
+    match (map.get(&Object::Keyword(keyword)), args.get(1)) {
+        (Some(obj), _) => Ok(obj.clone()),
+        (None, Some(default)) => Ok(default.clone()),
+        (None, None) => Ok(Object::Nil),
+    }
+}
+
+fn eval_eval(nodes: &[Object], rc_env: &Rc<RefCell<Option<Environment>>>) -> Result<Object, Error> {
+    // e.g.
+    // (eval (read-string "(add 1 2)"))
+    // (eval (read-string "(add a b)") {:a 1 :b 2})
+    //
+    // 先对参数求值，然后把第 1 个参数的值（即数据形式的表达式）在当前环境里求值，
+    // 所以表达式里定义的标识符在 eval 之后仍然可见（跟 load-file 一样）；
+    // 第 2 个参数为映射表，其键为关键字或者字符串，表示求值时额外定义的标识符，
+    // 这些标识符位于一个新的 scope（其父 scope 为当前的环境），表达式在这个 scope 里求值，
+    // 所以表达式里定义的标识符只在这次求值期间可见，不会影响当前的环境。
+    //
+    // 注：内置函数不持有环境，所以 eval 作为特殊形式实现，不能作为值传递。
+
+    if nodes.len() != 1 && nodes.len() != 2 {
+        return Err(Error::SyntaxError(
+            "expected 1 or 2 sub-expressions for the EVAL expression".to_string(),
+        ));
+    }
+
+    let form = eval(&nodes[0], rc_env)?;
+
+    let bindings = match nodes.get(1) {
+        Some(node) => match eval(node, rc_env)? {
+            Object::Map(map) => map,
+            obj => return Err(Error::type_error("map", &obj).in_function("eval").at_argument(2)),
         },
-        _ => Err(Error::EvalError("expected a function".to_string())),
+        None => return eval(&form, rc_env),
+    };
+
+    let mut records = HashMap::<String, Object>::new();
+    for (key, value) in bindings.into_inner() {
+        let name = match &key {
+            Object::Keyword(k) => k.name().to_string(),
+            Object::String(s) => s.clone(),
+            _ => {
+                return Err(Error::ValueError(format!(
+                    "expected keyword or string keys in the bindings of EVAL expression, got {}",
+                    key.to_readable_string()
+                )))
+            }
+        };
+        records.insert(name, value);
+    }
+
+    let eval_env = Environment::new_with_records(records, rc_env);
+    eval(&form, &eval_env.to_rc_env())
+}
+
+fn eval_load_file(nodes: &[Object], rc_env: &Rc<RefCell<Option<Environment>>>) -> Result<Object, Error> {
+    // e.g. (load-file "lib/math.cjs")
+    //
+    // 读取并解析脚本文件（相对路径基于当前工作目录），在当前环境里依次对其中的表达式求值，
+    // 所以脚本里定义的函数在 load-file 之后可以直接使用。返回最后一个表达式的值，空文件返回 nil。
+    // 只有允许访问文件系统的环境（见 Environment::new_global_with_io）才能使用 load-file。
+
+    if nodes.len() != 1 {
+        return Err(Error::SyntaxError(
+            "expected 1 sub-expression for the LOAD-FILE expression".to_string(),
+        ));
+    }
+
+    if !rc_env_io_enabled(rc_env) {
+        return Err(Error::IoError(
+            "load-file is not allowed because file I/O is not enabled".to_string(),
+        ));
+    }
+
+    let path = match eval(&nodes[0], rc_env)? {
+        Object::String(s) => s,
+        obj => return Err(Error::type_error("string", &obj).in_function("load-file").at_argument(1)),
+    };
+
+    let program = std::fs::read_to_string(&path)
+        .map_err(|err| Error::IoError(format!("cannot read file {}: {}", path, err)))?;
+
+    let mut result = Object::Nil;
+    for object in read_from_string(strip_shebang(&program))? {
+        result = eval(&object, rc_env)?;
     }
+    Ok(result)
 }
 
-// 解析一个字符串
+// 解析一个字符串里的所有表达式
+pub fn read_from_string(program: &str) -> Result<Vec<Object>, Error> {
+    let tokens = tokenize(program);
+    let mut remain_tokens = tokens.as_slice();
+    let mut objects: Vec<Object> = vec![];
+
+    while !remain_tokens.is_empty() {
+        let (object, rest_tokens) = parse(remain_tokens)?;
+        objects.push(object);
+        remain_tokens = rest_tokens;
+    }
+
+    Ok(objects)
+}
+
+// 对已经解析的表达式求值
+pub fn eval_object(object: &Object, rc_env: &Rc<RefCell<Option<Environment>>>) -> Result<Object, Error> {
+    eval(object, rc_env)
+}
+
+// 解析一个字符串，并依次对其中的表达式求值，返回最后一个表达式的值
 pub fn eval_from_string(
     program: &str,
     rc_env: &Rc<RefCell<Option<Environment>>>,
 ) -> Result<Object, Error> {
-    let tokens = tokenize(program);
-    let (object, rest_tokens) = parse(&tokens)?;
+    let objects = read_from_string(program)?;
 
-    if rest_tokens.len() > 0 {
-        return Err(Error::EvalError("invalid expression".to_string()));
+    if objects.is_empty() {
+        return Err(Error::ParseError("required at least one token".to_string()));
     }
 
-    let value = eval(&object, rc_env)?;
+    let mut value = Object::Nil;
+    for object in &objects {
+        value = eval(object, rc_env)?;
+    }
 
     Ok(value)
 }
@@ -1,58 +1,79 @@
-use std::{fs, io, rc::Rc, cell::RefCell};
-
 use env::Environment;
 
-use crate::error::Error;
-
 mod token;
 mod parser;
+mod pattern;
+mod repl;
+mod suggestion;
 pub mod ast;
 pub mod env;
 pub mod error;
 pub mod eval;
+pub mod output;
 
-fn read_line() -> String {
-    let mut line = String::new();
-    io::stdin().read_line(&mut line).expect("read line failed");
-    line
-}
+pub use repl::repl;
 
-pub fn repl() {
-    println!("toy lisp");
+// 进程的退出状态
+pub const EXIT_SUCCESS: i32 = 0;
+pub const EXIT_RUNTIME_ERROR: i32 = 1; // 求值时的错误
+pub const EXIT_PARSE_ERROR: i32 = 2; // 分词或者解析时的错误，此时程序不会被执行
 
-    let env = Environment::new_global();
+// 执行一段程序（比如脚本文件的内容），输出最后一个表达式的值，返回进程的退出状态，
+// 如果程序调用了 exit 函数，则立即结束并返回其指定的退出状态
+// argv 为传给程序的参数，以字符串向量的形式绑定到全局的标识符 `*argv*`
+pub fn run(program: &str, argv: &[String]) -> i32 {
+    // 命令行运行的脚本由用户自己提供，所以允许访问文件系统
+    let env = Environment::new_global_with_io();
     let rc_env = env.to_rc_env(); // Rc::new(RefCell::new(Some(env)));
 
-    loop {
-        println!("> ");
-        let text = read_line();
-        match eval_program(&text, &rc_env) {
-            Ok(res) => println!("{}", res),
-            Err(err) => match err {
-                Error::EvalError(msg) => println!("{}", msg),
-            },
-        }
-    }
-}
+    let argv_object = ast::Object::Vector(argv.iter().map(|a| ast::Object::String(a.clone())).collect());
+    env::rc_env_define(&rc_env, "*argv*", argv_object).expect("define *argv* failed");
 
-pub fn run(filepath: &str) {
-    let text = fs::read_to_string(filepath).expect("read file error");
+    // 先解析整个程序，有语法错误时不执行任何表达式
+    let objects = match eval::read_from_string(token::strip_shebang(program)) {
+        Ok(objects) => objects,
+        Err(err) => {
+            print_error(&err);
+            return EXIT_PARSE_ERROR;
+        }
+    };
 
-    let env = Environment::new_global();
-    let rc_env = env.to_rc_env(); // Rc::new(RefCell::new(Some(env)));
+    let mut result = ast::Object::Nil;
+    for object in &objects {
+        match eval::eval_object(object, &rc_env) {
+            Ok(obj) => result = obj,
+            Err(err) => {
+                if let Some(status) = err.exit_status() {
+                    return status;
+                }
+                print_error(&err);
+                return EXIT_RUNTIME_ERROR;
+            }
+        }
+    }
 
-    match eval_program(&text, &rc_env) {
-        Ok(res) => println!("{}", res),
-        Err(err) => match err {
-            Error::EvalError(msg) => println!("{}", msg),
-        },
+    match output::write_output(&format!("{}\n", result)) {
+        Ok(_) => EXIT_SUCCESS,
+        Err(err) => {
+            print_error(&err);
+            EXIT_RUNTIME_ERROR
+        }
     }
 }
 
-fn eval_program(
-    program: &str,
-    rc_env: &Rc<RefCell<Option<Environment>>>,
-) -> Result<ast::Object, error::Error> {
+// 输出错误信息到标准错误，如果错误带有调用栈，则先按从最外层到最内层的顺序输出调用栈，
+// 被用户中断的求值不输出调用栈
+fn print_error(err: &error::Error) {
+    let mut text = String::new();
+    let frames = err.stack_trace();
+    if !frames.is_empty() && !matches!(err.root(), error::Error::Interrupted) {
+        text.push_str("traceback (outermost call first):\n");
+        for frame in frames {
+            text.push_str(&format!("  at {}\n", frame));
+        }
+    }
+    text.push_str(&format!("{}\n", err));
 
-    eval::eval_from_string(program, rc_env)
+    // 标准错误本身无法写入时已经没有别的地方可以报告了，忽略该错误
+    let _ = output::write_error_output(&text);
 }
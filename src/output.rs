@@ -0,0 +1,71 @@
+// 程序的输出
+//
+// 内置函数 print、println 以及脚本运行结束时输出的值写到“标准输出”，
+// eprint、eprintln 以及错误信息写到“标准错误”。
+// 默认情况下它们分别就是进程的标准输出和标准错误，嵌入解释器的程序（以及测试）
+// 可以通过 set_output、set_error_output 替换为其他的目标，或者使用 capture_output 捕获输出。
+//
+// 注：内置函数不持有环境，所以输出的目标以线程局部变量的形式保存。
+
+use std::{
+    cell::RefCell,
+    io::{self, Write},
+    rc::Rc,
+};
+
+use crate::error::Error;
+
+thread_local! {
+    static OUTPUT: RefCell<Box<dyn Write>> = RefCell::new(Box::new(io::stdout()));
+    static ERROR_OUTPUT: RefCell<Box<dyn Write>> = RefCell::new(Box::new(io::stderr()));
+}
+
+// 替换标准输出的目标，返回原来的目标
+pub fn set_output(sink: Box<dyn Write>) -> Box<dyn Write> {
+    OUTPUT.with(|output| output.replace(sink))
+}
+
+// 替换标准错误的目标，返回原来的目标
+pub fn set_error_output(sink: Box<dyn Write>) -> Box<dyn Write> {
+    ERROR_OUTPUT.with(|output| output.replace(sink))
+}
+
+// 执行 f 并捕获其间写到标准输出的内容，执行完毕之后恢复原来的目标
+pub fn capture_output<R>(f: impl FnOnce() -> R) -> (R, String) {
+    let buffer = SharedBuffer::default();
+    let previous = set_output(Box::new(buffer.clone()));
+    let result = f();
+    set_output(previous);
+
+    let text = String::from_utf8_lossy(&buffer.0.borrow()).into_owned();
+    (result, text)
+}
+
+pub(crate) fn write_output(text: &str) -> Result<(), Error> {
+    OUTPUT.with(|output| write_to(&mut **output.borrow_mut(), text))
+}
+
+pub(crate) fn write_error_output(text: &str) -> Result<(), Error> {
+    ERROR_OUTPUT.with(|output| write_to(&mut **output.borrow_mut(), text))
+}
+
+// 每次写入之后都立即 flush，使得不以换行结尾的内容（比如 print 的输出）也能及时显示
+fn write_to(sink: &mut dyn Write, text: &str) -> Result<(), Error> {
+    sink.write_all(text.as_bytes())
+        .and_then(|_| sink.flush())
+        .map_err(|err| Error::RuntimeError(format!("write output failed: {}", err)))
+}
+
+// 用于捕获输出的缓冲区，capture_output 持有它的一个副本以便读取写入的内容
+#[derive(Clone, Default)]
+struct SharedBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
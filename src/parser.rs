@@ -1,33 +1,98 @@
-use crate::{ast::Object, error::Error};
+use im_rc::{OrdMap, OrdSet};
+
+use crate::{
+    ast::{Keyword, Object, SourceOrdered},
+    error::Error,
+};
 
 // 解析一系列 tokens，返回 ast::Object 和剩余的 tokens
 pub fn parse(tokens: &[String]) -> Result<(Object, &[String]), Error> {
     let (token, rest_tokens) = tokens
         .split_first()
-        .ok_or(Error::EvalError("required at least one token".to_string()))?;
+        .ok_or(Error::ParseError("required at least one token".to_string()))?;
 
     match token.as_str() {
         "(" => parse_list(rest_tokens),
-        ")" => Err(Error::EvalError("unexpected right paren".to_string())),
-        _ => Ok((parse_single_object(token), rest_tokens)),
+        "[" => parse_vector(rest_tokens),
+        "{" => parse_map(rest_tokens),
+        "#{" => parse_set(rest_tokens),
+        ")" => Err(Error::ParseError("unexpected right paren".to_string())),
+        "]" => Err(Error::ParseError("unexpected right bracket".to_string())),
+        "}" => Err(Error::ParseError("unexpected right brace".to_string())),
+        _ => Ok((parse_single_object(token)?, rest_tokens)),
     }
 }
 
 // 解析列表，返回 ast::Object::List 和剩余的 tokens
 fn parse_list(tokens: &[String]) -> Result<(Object, &[String]), Error> {
+    let (objects, rest_tokens) = parse_sequence(tokens, ")", "missing right paren")?;
+    Ok((Object::List(objects), rest_tokens))
+}
+
+// 解析向量，返回 ast::Object::Vector 和剩余的 tokens
+fn parse_vector(tokens: &[String]) -> Result<(Object, &[String]), Error> {
+    let (objects, rest_tokens) = parse_sequence(tokens, "]", "missing right bracket")?;
+    Ok((Object::Vector(objects.into_iter().collect()), rest_tokens))
+}
+
+// 解析映射表，返回 ast::Object::Map 和剩余的 tokens
+// 映射表的字面量由成对的键和值组成，如 `{k1 v1 k2 v2}`，
+// 映射表记录各个键在源码里的顺序，使得求值时按源码的顺序对各项求值
+fn parse_map(tokens: &[String]) -> Result<(Object, &[String]), Error> {
+    let (objects, rest_tokens) = parse_sequence(tokens, "}", "missing right brace")?;
+
+    if objects.len() % 2 != 0 {
+        return Err(Error::ParseError(
+            "map literal must contain an even number of forms".to_string(),
+        ));
+    }
+
+    let mut map = OrdMap::new();
+    let mut order = vec![];
+    let mut iter = objects.into_iter();
+    while let (Some(key), Some(value)) = (iter.next(), iter.next()) {
+        if map.insert(key.clone(), value).is_some() {
+            return Err(Error::ParseError(format!("duplicate key in map literal: {}", key.to_readable_string())));
+        }
+        order.push(key);
+    }
+
+    Ok((Object::Map(SourceOrdered::with_source_order(map, order)), rest_tokens))
+}
+
+// 解析集合，返回 ast::Object::Set 和剩余的 tokens，跟映射表一样记录各个元素在源码里的顺序
+fn parse_set(tokens: &[String]) -> Result<(Object, &[String]), Error> {
+    let (objects, rest_tokens) = parse_sequence(tokens, "}", "missing right brace")?;
+
+    let mut set = OrdSet::new();
+    for object in &objects {
+        if set.insert(object.clone()).is_some() {
+            return Err(Error::ParseError(format!("duplicate item in set literal: {}", object.to_readable_string())));
+        }
+    }
+
+    Ok((Object::Set(SourceOrdered::with_source_order(set, objects)), rest_tokens))
+}
+
+// 解析一组元素直到遇到结束符号 `close`，返回元素列表和剩余的 tokens
+fn parse_sequence<'a>(
+    tokens: &'a [String],
+    close: &str,
+    missing_message: &str,
+) -> Result<(Vec<Object>, &'a [String]), Error> {
     let mut objects: Vec<Object> = vec![];
     let mut remain_tokens = tokens;
 
     loop {
         let (token, rest_tokens) = remain_tokens
             .split_first()
-            .ok_or(Error::EvalError("missing right paren".to_string()))?;
+            .ok_or(Error::ParseError(missing_message.to_string()))?;
 
-        if token == ")" {
-            return Ok((Object::List(objects), rest_tokens));
+        if token == close {
+            return Ok((objects, rest_tokens));
         }
 
-        let (object, remain_tokens_after_parse) = parse(&remain_tokens)?;
+        let (object, remain_tokens_after_parse) = parse(remain_tokens)?;
         objects.push(object);
 
         remain_tokens = remain_tokens_after_parse;
@@ -35,17 +100,82 @@ fn parse_list(tokens: &[String]) -> Result<(Object, &[String]), Error> {
 }
 
 // 解析单独一个元素，返回 ast::Object
-// 目前单独元素只支持 `整型` 和 `布尔型` 两种
-fn parse_single_object(token: &String) -> Object {
-    match token.as_str() {
+// 目前单独元素支持 `nil`、`整型`、`布尔型`、`字符`、`字符串`、`关键字` 和 `标识符`
+fn parse_single_object(token: &str) -> Result<Object, Error> {
+    let object = match token {
+        "nil" => Object::Nil,
         "true" => Object::Bool(true),
         "false" => Object::Bool(false),
+        _ if token.starts_with('"') => Object::String(parse_string(token)?),
+        _ if token.starts_with("#\\") => Object::Char(parse_char(&token[2..])?),
+        _ if token.len() > 1 && token.starts_with(':') => Object::Keyword(Keyword::intern(&token[1..])),
         _ => {
             let maybe_number = token.parse::<i64>();
             match maybe_number {
                 Ok(i) => Object::Number(i),
-                _ => Object::Symbol(token.clone()),
+                _ => Object::Symbol(token.to_string()),
+            }
+        }
+    };
+
+    Ok(object)
+}
+
+// 解析字符串字面量，token 包括首尾的双引号
+// 支持的转义有 `\"`、`\\`、`\n`、`\t` 和 `\r`
+fn parse_string(token: &str) -> Result<String, Error> {
+    let mut value = String::new();
+    let mut chars = token[1..].chars();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                return if chars.next().is_none() {
+                    Ok(value)
+                } else {
+                    Err(Error::ParseError(format!("invalid string literal: {}", token)))
+                };
+            }
+            '\\' => {
+                let escaped = match chars.next() {
+                    Some('"') => '"',
+                    Some('\\') => '\\',
+                    Some('n') => '\n',
+                    Some('t') => '\t',
+                    Some('r') => '\r',
+                    _ => {
+                        return Err(Error::ParseError(format!(
+                            "invalid escape in string literal: {}",
+                            token
+                        )))
+                    }
+                };
+                value.push(escaped);
             }
+            _ => value.push(c),
         }
     }
+
+    Err(Error::ParseError("missing closing quote".to_string()))
+}
+
+// 解析字符字面量，name 为 `#\` 后面的部分，
+// 如 `a`、`space`、`newline`、`tab` 以及 Unicode 码点形式 `u{1F600}`
+fn parse_char(name: &str) -> Result<char, Error> {
+    let mut chars = name.chars();
+    if let (Some(c), None) = (chars.next(), chars.next()) {
+        return Ok(c);
+    }
+
+    match name {
+        "space" => Ok(' '),
+        "newline" => Ok('\n'),
+        "tab" => Ok('\t'),
+        _ => name
+            .strip_prefix("u{")
+            .and_then(|rest| rest.strip_suffix('}'))
+            .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+            .and_then(char::from_u32)
+            .ok_or(Error::ParseError(format!("invalid character literal: #\\{}", name))),
+    }
 }
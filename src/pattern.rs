@@ -0,0 +1,251 @@
+// 解构绑定的模式
+//
+// 模式就是 AST 的节点（即 Object），支持：
+// - 标识符，如 `a`，绑定整个值；`_` 表示忽略该值；
+// - 列表或者向量，如 `(a b)`、`[a [b c] &rest others]`，解构列表或者向量，
+//   `&rest` 后面的模式绑定剩余元素组成的列表；
+// - 映射表，如 `{:keys (name age)}`、`{(x y) :point}`，解构映射表，
+//   `:keys` 把同名关键字的值绑定到各个标识符，其他的项则以值为键，
+//   把对应的值按键所对应的模式解构，键不存在时其值为 nil。
+//
+// 模式可以嵌套。
+//
+// match 表达式的模式还可以是字面量（nil、布尔值、数字、字符、字符串和关键字），
+// 只有相等的值才能匹配。
+
+use crate::{
+    ast::{Keyword, Object},
+    error::{abbreviate, Error},
+};
+
+// 检查解构模式的格式，返回模式里所有被绑定的标识符的名称
+pub fn pattern_names(pattern: &Object) -> Result<Vec<String>, Error> {
+    let mut names = vec![];
+    collect_names(pattern, false, &mut names)?;
+    Ok(names)
+}
+
+// 检查 match 表达式的模式（允许字面量）的格式，返回模式里所有被绑定的标识符的名称
+pub fn match_pattern_names(pattern: &Object) -> Result<Vec<String>, Error> {
+    let mut names = vec![];
+    collect_names(pattern, true, &mut names)?;
+    Ok(names)
+}
+
+fn is_literal(obj: &Object) -> bool {
+    matches!(
+        obj,
+        Object::Nil
+            | Object::Bool(_)
+            | Object::Number(_)
+            | Object::Char(_)
+            | Object::String(_)
+            | Object::Keyword(_)
+    )
+}
+
+fn collect_names(pattern: &Object, allow_literal: bool, names: &mut Vec<String>) -> Result<(), Error> {
+    match pattern {
+        _ if allow_literal && is_literal(pattern) => Ok(()),
+        Object::Symbol(s) if s == "_" => Ok(()),
+        Object::Symbol(s) if !s.starts_with('&') => {
+            if names.contains(s) {
+                return Err(Error::SyntaxError(format!("duplicate name in pattern: {}", s)));
+            }
+            names.push(s.clone());
+            Ok(())
+        }
+        Object::List(_) | Object::Vector(_) => {
+            let items: Vec<&Object> = sequence_items(pattern);
+            let (fixed, rest) = split_rest(&items)?;
+            for item in fixed.iter().chain(rest.iter()) {
+                collect_names(item, allow_literal, names)?;
+            }
+            Ok(())
+        }
+        Object::Map(map) => {
+            for (key, value) in map {
+                match key {
+                    Object::Keyword(k) if *k == Keyword::intern("keys") => match value {
+                        Object::List(list) if list.iter().all(|i| matches!(i, Object::Symbol(_))) => {
+                            for item in list {
+                                collect_names(item, allow_literal, names)?;
+                            }
+                        }
+                        _ => {
+                            return Err(Error::SyntaxError(
+                                "expected a list of symbols after :keys in pattern".to_string(),
+                            ))
+                        }
+                    },
+                    _ => collect_names(key, allow_literal, names)?,
+                }
+            }
+            Ok(())
+        }
+        _ => Err(Error::SyntaxError(format!("invalid pattern: {}", pattern.to_readable_string()))),
+    }
+}
+
+// 按模式解构数据，把标识符及其对应的值依次追加到 bindings，
+// 当数据的形状跟模式不符时返回错误
+// 数据的种类跟模式不符（比如用列表的模式解构数字）
+fn shape_error(pattern: &Object, value: &Object, expected: &str) -> Error {
+    Error::MatchError(format!(
+        "cannot destructure {} with pattern {}: expected {}, got {}",
+        abbreviate(value.to_readable_string()),
+        pattern.to_readable_string(),
+        expected,
+        value.type_name()
+    ))
+}
+
+pub fn destructure(
+    pattern: &Object,
+    value: &Object,
+    bindings: &mut Vec<(String, Object)>,
+) -> Result<(), Error> {
+    match pattern {
+        _ if is_literal(pattern) => {
+            if pattern == value {
+                Ok(())
+            } else {
+                Err(Error::MatchError(format!(
+                    "expected {}, got {}",
+                    pattern.to_readable_string(),
+                    abbreviate(value.to_readable_string())
+                )))
+            }
+        }
+        Object::Symbol(s) if s == "_" => Ok(()),
+        Object::Symbol(s) => {
+            bindings.push((s.clone(), value.clone()));
+            Ok(())
+        }
+        Object::List(_) | Object::Vector(_) => {
+            let values: Vec<&Object> = match value {
+                Object::List(_) | Object::Vector(_) => sequence_items(value),
+                _ => return Err(shape_error(pattern, value, "list or vector")),
+            };
+
+            let items = sequence_items(pattern);
+            let (fixed, rest) = split_rest(&items)?;
+
+            let length_matched = match rest {
+                Some(_) => values.len() >= fixed.len(),
+                None => values.len() == fixed.len(),
+            };
+            if !length_matched {
+                return Err(Error::MatchError(format!(
+                    "cannot destructure {} with pattern {}: expected {}{} {}, got {}",
+                    abbreviate(value.to_readable_string()),
+                    pattern.to_readable_string(),
+                    if rest.is_some() { "at least " } else { "" },
+                    fixed.len(),
+                    if fixed.len() == 1 { "item" } else { "items" },
+                    values.len()
+                )));
+            }
+
+            for (item, v) in fixed.iter().zip(values.iter()) {
+                destructure(item, v, bindings)?;
+            }
+
+            if let Some(rest) = rest {
+                let rest_values = values[fixed.len()..].iter().map(|v| (*v).clone()).collect();
+                destructure(rest, &Object::List(rest_values), bindings)?;
+            }
+
+            Ok(())
+        }
+        Object::Map(entries) => {
+            let map = match value {
+                Object::Map(map) => map,
+                _ => return Err(shape_error(pattern, value, "map")),
+            };
+
+            let lookup = |key: &Object| map.get(key).cloned().unwrap_or(Object::Nil);
+
+            for (key, item) in entries {
+                match (key, item) {
+                    (Object::Keyword(k), Object::List(list)) if *k == Keyword::intern("keys") => {
+                        for name in list {
+                            let v = lookup(&Object::Keyword(Keyword::intern(&name.to_string())));
+                            destructure(name, &v, bindings)?;
+                        }
+                    }
+                    _ => destructure(key, &lookup(item), bindings)?,
+                }
+            }
+
+            Ok(())
+        }
+        _ => Err(Error::SyntaxError(format!("invalid pattern: {}", pattern.to_readable_string()))),
+    }
+}
+
+fn sequence_items(obj: &Object) -> Vec<&Object> {
+    match obj {
+        Object::List(list) => list.iter().collect(),
+        Object::Vector(vector) => vector.iter().collect(),
+        _ => vec![],
+    }
+}
+
+// 把列表或者向量模式的元素拆分为固定部分和 `&rest` 后面的模式
+fn split_rest<'a>(items: &[&'a Object]) -> Result<(Vec<&'a Object>, Option<&'a Object>), Error> {
+    let is_rest = |item: &&Object| matches!(item, Object::Symbol(s) if s == "&rest");
+
+    match items.iter().position(is_rest) {
+        None => Ok((items.to_vec(), None)),
+        Some(idx) if idx + 2 == items.len() => Ok((items[..idx].to_vec(), Some(items[idx + 1]))),
+        Some(_) => Err(Error::SyntaxError(
+            "expected exactly one pattern after &rest".to_string(),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{destructure, match_pattern_names, pattern_names};
+    use crate::ast::Object;
+    use crate::parser::parse;
+    use crate::token::tokenize;
+
+    fn read(text: &str) -> Object {
+        let tokens = tokenize(text);
+        parse(&tokens).expect("parse failed").0
+    }
+
+    #[test]
+    fn test_pattern_names() {
+        let names = pattern_names(&read("(a [b _] {:keys (c) d :d} &rest e)")).unwrap();
+        // 映射表模式的项按键排序，所以 d 在 :keys 之前
+        assert_eq!(names, vec!["a", "b", "d", "c", "e"]);
+
+        assert!(pattern_names(&read("(a a)")).is_err());
+        assert!(pattern_names(&read("(a &rest)")).is_err());
+        assert!(pattern_names(&read("(a 1)")).is_err());
+
+        let names = match_pattern_names(&read("(:add a {:admin :role} [1 nil] &rest _)")).unwrap();
+        assert_eq!(names, vec!["a"]);
+    }
+
+    #[test]
+    fn test_destructure() {
+        let mut bindings = vec![];
+        destructure(
+            &read("(a [b c] &rest d)"),
+            &read("[1 (2 3) 4 5]"),
+            &mut bindings,
+        )
+        .unwrap();
+
+        let text: Vec<String> = bindings.iter().map(|(n, v)| format!("{}={}", n, v)).collect();
+        assert_eq!(text, vec!["a=1", "b=2", "c=3", "d=(4 5)"]);
+
+        assert!(destructure(&read("(a b)"), &read("[1]"), &mut vec![]).is_err());
+        assert!(destructure(&read("(a b)"), &read("1"), &mut vec![]).is_err());
+        assert!(destructure(&read("(:add a)"), &read("(:sub 1)"), &mut vec![]).is_err());
+    }
+}
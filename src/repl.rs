@@ -0,0 +1,461 @@
+// REPL（交互模式）
+//
+// 使用 rustyline 提供行编辑、历史记录、Tab 补全以及括号匹配高亮，
+// 另外还支持以冒号开头的元命令，如 `:help`、`:env`、`:time expr` 等。
+// 历史记录保存在用户主目录的 `.toy_lisp_history` 文件。
+
+use std::{borrow::Cow, cell::RefCell, env, fs, path::PathBuf, rc::Rc, time::Instant};
+
+use rustyline::{
+    completion::Completer,
+    error::ReadlineError,
+    highlight::{Highlighter, MatchingBracketHighlighter},
+    hint::Hinter,
+    validate::Validator,
+    Context, Editor, Helper,
+};
+
+use crate::{
+    ast::Object,
+    env::{rc_env_lookup, rc_env_names, Environment},
+    error::Error,
+    eval::{
+        clear_interrupt, eval_from_string, eval_object, eval_steps, interrupt, read_from_string,
+        SPECIAL_FORMS,
+    },
+    print_error,
+    token::{strip_shebang, tokenize},
+};
+
+const HISTORY_FILE_NAME: &str = ".toy_lisp_history";
+
+struct LispHelper {
+    rc_env: Rc<RefCell<Option<Environment>>>,
+    bracket_highlighter: MatchingBracketHighlighter,
+}
+
+impl Completer for LispHelper {
+    type Candidate = String;
+
+    // 补全光标前的标识符，候选项为当前已定义的标识符以及特殊形式的名称
+    fn complete(
+        &self,
+        line: &str,
+        pos: usize,
+        _ctx: &Context<'_>,
+    ) -> rustyline::Result<(usize, Vec<String>)> {
+        let names = rc_env_names(&self.rc_env)
+            .into_iter()
+            .chain(SPECIAL_FORMS.iter().map(|s| s.to_string()))
+            .chain(command_names().map(|s| s.to_string()));
+        Ok(complete_name(line, pos, names))
+    }
+}
+
+// 找出光标前的（不完整的）标识符的起始位置，以及以其为前缀的候选名称
+fn complete_name(line: &str, pos: usize, names: impl Iterator<Item = String>) -> (usize, Vec<String>) {
+    let start = line[..pos]
+        .rfind(|c: char| c.is_whitespace() || "()[]{}\"".contains(c))
+        .map_or(0, |idx| idx + 1);
+    let prefix = &line[start..pos];
+
+    if prefix.is_empty() {
+        return (start, vec![]);
+    }
+
+    let mut candidates: Vec<String> = names.filter(|name| name.starts_with(prefix)).collect();
+    candidates.sort();
+    candidates.dedup();
+
+    (start, candidates)
+}
+
+impl Hinter for LispHelper {
+    type Hint = String;
+}
+
+impl Highlighter for LispHelper {
+    fn highlight<'l>(&self, line: &'l str, pos: usize) -> Cow<'l, str> {
+        self.bracket_highlighter.highlight(line, pos)
+    }
+
+    fn highlight_char(&self, line: &str, pos: usize) -> bool {
+        self.bracket_highlighter.highlight_char(line, pos)
+    }
+}
+
+impl Validator for LispHelper {}
+
+impl Helper for LispHelper {}
+
+// 历史记录文件的路径，找不到用户主目录时不保存历史记录
+fn history_path() -> Option<PathBuf> {
+    env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE_NAME))
+}
+
+// 检查输入的内容是否不完整，即是否有未闭合的括号或者未结束的字符串字面量
+fn is_incomplete(text: &str) -> bool {
+    let mut depth: i64 = 0;
+
+    for token in tokenize(text) {
+        match token.as_str() {
+            "(" | "[" | "{" | "#{" => depth += 1,
+            ")" | "]" | "}" => depth -= 1,
+            t if t.starts_with('"') && !is_closed_string(t) => return true,
+            _ => {}
+        }
+    }
+
+    depth > 0
+}
+
+// 字符串字面量是否以未被转义的双引号结束
+fn is_closed_string(token: &str) -> bool {
+    match token[1..].strip_suffix('"') {
+        Some(content) => content.chars().rev().take_while(|c| *c == '\\').count().is_multiple_of(2),
+        None => false,
+    }
+}
+
+// 依次对输入的每一个表达式求值并以可读形式输出结果（比如字符串带双引号），遇到错误时停止
+fn eval_and_print(text: &str, rc_env: &Rc<RefCell<Option<Environment>>>) -> Option<i32> {
+    let objects = match read_from_string(text) {
+        Ok(objects) => objects,
+        Err(err) => return report_error(&err),
+    };
+
+    for object in &objects {
+        match eval_object(object, rc_env) {
+            Ok(res) => println!("{}", res.to_readable_string()),
+            Err(err) => return report_error(&err),
+        }
+    }
+    None
+}
+
+// 输出错误信息；如果是程序调用了 exit 函数，则不输出，而是返回其退出状态，由 REPL 结束运行
+fn report_error(err: &Error) -> Option<i32> {
+    if err.exit_status().is_none() {
+        print_error(err);
+    }
+    err.exit_status()
+}
+
+// REPL 的元命令及其说明
+const COMMANDS: &[(&str, &str)] = &[
+    (":help", "show this help"),
+    (":env", "list the global bindings and their types"),
+    (":type <expr>", "show the type of the value of an expression"),
+    (":time <expr>", "evaluate an expression, show the elapsed time and eval steps"),
+    (":load <file>", "load and evaluate a script file"),
+    (":reload", "reset the environment and load the last loaded file again"),
+    (":reset", "reset the global environment"),
+    (":ast <expr>", "show the parsed syntax tree of an expression"),
+    (":tokens <expr>", "show the tokens of an expression"),
+    (":quit", "exit the REPL"),
+];
+
+// 元命令的名称，如 ":help"
+fn command_names() -> impl Iterator<Item = &'static str> {
+    COMMANDS.iter().map(|(usage, _)| usage.split(' ').next().unwrap_or(usage))
+}
+
+// 如果输入的内容是元命令，则返回命令的名称及其参数
+// 注：关键字（如 `:name`）也以冒号开头，所以只有已知的命令名称才被当作元命令
+fn parse_command(text: &str) -> Option<(&str, &str)> {
+    let text = text.trim();
+    let (name, argument) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+    command_names()
+        .any(|n| n == name)
+        .then(|| (name, argument.trim()))
+}
+
+// REPL 的状态
+struct Session {
+    rc_env: Rc<RefCell<Option<Environment>>>,
+    loaded_file: Option<String>,
+}
+
+impl Session {
+    // 以新的全局环境替换当前的全局环境，
+    // 原地替换使得 Tab 补全等持有同一个 rc_env 的地方也能看到新的环境
+    fn reset(&self) {
+        *self.rc_env.borrow_mut() = Some(Environment::new_global_with_io());
+    }
+
+    fn load(&mut self, path: &str) -> Option<i32> {
+        let text = match fs::read_to_string(path) {
+            Ok(text) => text,
+            Err(err) => {
+                // 跟其他错误一样输出到标准错误
+                print_error(&Error::IoError(format!("cannot read file {}: {}", path, err)));
+                return None;
+            }
+        };
+
+        self.loaded_file = Some(path.to_string());
+        eval_and_print(strip_shebang(&text), &self.rc_env)
+    }
+}
+
+// 执行元命令，如果命令执行的程序调用了 exit 函数，则返回其退出状态
+fn run_command(name: &str, argument: &str, session: &mut Session) -> Option<i32> {
+    match name {
+        ":help" => {
+            for (usage, description) in COMMANDS {
+                println!("{:<16}{}", usage, description);
+            }
+        }
+        ":env" => {
+            let mut names = rc_env_names(&session.rc_env);
+            names.sort();
+            let width = names.iter().map(|n| n.chars().count()).max().unwrap_or(0);
+            for name in names {
+                if let Some(obj) = rc_env_lookup(&session.rc_env, &name) {
+                    println!("{:<width$}  {}", name, obj.type_name(), width = width);
+                }
+            }
+        }
+        ":type" => match eval_from_string(argument, &session.rc_env) {
+            Ok(obj) => println!("{}", obj.type_name()),
+            Err(err) => return report_error(&err),
+        },
+        ":time" => {
+            let start_steps = eval_steps();
+            let start_time = Instant::now();
+            let result = eval_from_string(argument, &session.rc_env);
+            let elapsed = start_time.elapsed();
+            let steps = eval_steps() - start_steps;
+
+            let status = match result {
+                Ok(obj) => {
+                    println!("{}", obj.to_readable_string());
+                    None
+                }
+                Err(err) => report_error(&err),
+            };
+            println!("elapsed: {:?}, eval steps: {}", elapsed, steps);
+            return status;
+        }
+        ":load" if argument.is_empty() => {
+            print_error(&Error::SyntaxError("usage: :load <file>".to_string()))
+        }
+        ":load" => return session.load(argument),
+        ":reload" => match session.loaded_file.clone() {
+            Some(path) => {
+                session.reset();
+                return session.load(&path);
+            }
+            None => print_error(&Error::RuntimeError("no file has been loaded".to_string())),
+        },
+        ":reset" => {
+            session.reset();
+            session.loaded_file = None;
+        }
+        ":ast" => match read_from_string(argument) {
+            Ok(objects) => {
+                for object in &objects {
+                    print!("{}", dump_ast(object, 0));
+                }
+            }
+            Err(err) => print_error(&err),
+        },
+        ":tokens" => println!("{:?}", tokenize(argument)),
+        _ => {}
+    }
+    None
+}
+
+// 以缩进的树状形式输出语法树，每个节点一行，显示节点的类型和值
+fn dump_ast(object: &Object, depth: usize) -> String {
+    let indent = "  ".repeat(depth);
+    let children: Vec<&Object> = match object {
+        Object::List(list) => list.iter().collect(),
+        Object::Vector(vector) => vector.iter().collect(),
+        Object::Map(map) => map.iter().flat_map(|(k, v)| [k, v]).collect(),
+        Object::Set(set) => set.iter().collect(),
+        _ => return format!("{}{} {}\n", indent, object.type_name(), object),
+    };
+
+    let mut text = format!("{}{}\n", indent, object.type_name());
+    for child in children {
+        text.push_str(&dump_ast(child, depth + 1));
+    }
+    text
+}
+
+// 运行 REPL，返回进程的退出状态
+pub fn repl() -> i32 {
+    println!("toy lisp");
+
+    let env = Environment::new_global_with_io();
+    let rc_env = env.to_rc_env(); // Rc::new(RefCell::new(Some(env)));
+
+    let mut editor = Editor::<LispHelper>::new();
+    editor.set_helper(Some(LispHelper {
+        rc_env: Rc::clone(&rc_env),
+        bracket_highlighter: MatchingBracketHighlighter::new(),
+    }));
+
+    // 求值期间按 Ctrl-C 只中断当前的求值，然后回到提示符，而不是结束进程。
+    // 注：读取输入期间终端处于 raw 模式，Ctrl-C 由 rustyline 处理，不会产生信号。
+    if let Err(err) = ctrlc::set_handler(interrupt) {
+        println!("set Ctrl-C handler failed: {}", err);
+    }
+
+    let mut session = Session {
+        rc_env: Rc::clone(&rc_env),
+        loaded_file: None,
+    };
+
+    let history_path = history_path();
+    if let Some(path) = &history_path {
+        // 首次运行时历史记录文件不存在，忽略错误
+        let _ = editor.load_history(path);
+    }
+
+    // 当前输入的内容，括号未闭合时继续读取下一行
+    let mut text = String::new();
+
+    // 程序调用 exit 函数时指定的退出状态
+    let mut exit_status = 0;
+
+    loop {
+        let prompt = if text.is_empty() { "> " } else { "... " };
+        let line = match editor.readline(prompt) {
+            Ok(line) => line,
+            // Ctrl-C 放弃当前输入的内容
+            Err(ReadlineError::Interrupted) => {
+                text.clear();
+                continue;
+            }
+            // Ctrl-D 或者输入结束时退出
+            Err(ReadlineError::Eof) => break,
+            Err(err) => {
+                println!("read line failed: {}", err);
+                break;
+            }
+        };
+
+        if !text.is_empty() {
+            text.push('\n');
+        }
+        text.push_str(&line);
+
+        if text.trim().is_empty() {
+            text.clear();
+            continue;
+        }
+        if is_incomplete(&text) {
+            continue;
+        }
+
+        editor.add_history_entry(text.as_str());
+        clear_interrupt();
+        let status = match parse_command(&text) {
+            Some((":quit", _)) => break,
+            Some((name, argument)) => run_command(name, argument, &mut session),
+            None => eval_and_print(&text, &rc_env),
+        };
+        text.clear();
+
+        if let Some(status) = status {
+            exit_status = status;
+            break;
+        }
+    }
+
+    if let Some(path) = &history_path {
+        if let Err(err) = editor.save_history(path) {
+            println!("save history failed: {}", err);
+        }
+    }
+
+    exit_status
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, io, rc::Rc};
+
+    use super::{complete_name, dump_ast, is_incomplete, parse_command, run_command, Session};
+    use crate::{env::Environment, eval::read_from_string, output::set_error_output};
+
+    // 用于捕获标准错误的缓冲区
+    #[derive(Clone, Default)]
+    struct Buffer(Rc<RefCell<Vec<u8>>>);
+
+    impl io::Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_complete_name() {
+        let names = || ["add", "and", "assoc", "let", "let*"].iter().map(|s| s.to_string());
+
+        assert_eq!(complete_name("(a", 2, names()), (1, vec![
+            "add".to_string(), "and".to_string(), "assoc".to_string()
+        ]));
+        assert_eq!(complete_name("(add 1 (le", 10, names()), (8, vec![
+            "let".to_string(), "let*".to_string()
+        ]));
+        assert_eq!(complete_name("(add ", 5, names()), (5, vec![]));
+    }
+
+    #[test]
+    fn test_parse_command() {
+        assert_eq!(parse_command(":help"), Some((":help", "")));
+        assert_eq!(parse_command(" :time  (add 1 2) "), Some((":time", "(add 1 2)")));
+        assert_eq!(parse_command(":name"), None);
+        assert_eq!(parse_command("(add 1 2)"), None);
+    }
+
+    #[test]
+    fn test_dump_ast() {
+        let objects = read_from_string("(add 1 [x :k])").unwrap();
+        assert_eq!(
+            dump_ast(&objects[0], 0),
+            "list\n  symbol add\n  number 1\n  vector\n    symbol x\n    keyword :k\n"
+        );
+    }
+
+    #[test]
+    fn test_is_incomplete() {
+        assert!(is_incomplete("(do (add 1"));
+        assert!(is_incomplete("[1 {2 #{3"));
+        assert!(is_incomplete(r#"(f "abc"#));
+        assert!(is_incomplete(r#"(f "a\""#));
+        assert!(!is_incomplete("(add 1 2) (sub 3 4)"));
+        assert!(!is_incomplete(r#"(f "a\\" #\()"#));
+        assert!(!is_incomplete("(add 1 2))"));
+    }
+
+    #[test]
+    fn test_load_error() {
+        let mut session = Session {
+            rc_env: Environment::new_global_with_io().to_rc_env(),
+            loaded_file: None,
+        };
+
+        let buffer = Buffer::default();
+        let previous = set_error_output(Box::new(buffer.clone()));
+        run_command(":load", "no-such-file.cjs", &mut session);
+        run_command(":load", "", &mut session);
+        run_command(":reload", "", &mut session);
+        set_error_output(previous);
+
+        // 加载失败的信息都输出到标准错误
+        let text = String::from_utf8_lossy(&buffer.0.borrow()).into_owned();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("io error: cannot read file no-such-file.cjs: "));
+        assert_eq!(lines[1], "syntax error: usage: :load <file>");
+        assert_eq!(lines[2], "runtime error: no file has been loaded");
+    }
+}
@@ -0,0 +1,84 @@
+// 从候选名称当中找出跟指定名称最接近的几个，用于 "did you mean" 提示
+//
+// 名称之间的距离使用 "最优字符串对齐距离"（即允许相邻字符交换的编辑距离），
+// 所以像 `lte`/`let`、`fbi`/`fib` 这样的相邻字符颠倒只算一次编辑。
+pub fn suggest<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> Vec<String> {
+    const MAX_SUGGESTIONS: usize = 3;
+
+    // 名称越长，允许的编辑次数越多，但最多 3 次
+    let max_distance = name.chars().count().div_ceil(3).min(3);
+
+    let mut matches: Vec<(usize, &str)> = candidates
+        .filter(|candidate| *candidate != name)
+        .map(|candidate| (edit_distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .collect();
+
+    matches.sort();
+    matches.dedup();
+
+    // 只保留距离最近的那些名称
+    let closest = match matches.first() {
+        Some((distance, _)) => *distance,
+        None => return vec![],
+    };
+
+    matches
+        .into_iter()
+        .take_while(|(distance, _)| *distance == closest)
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, candidate)| candidate.to_string())
+        .collect()
+}
+
+fn edit_distance(left: &str, right: &str) -> usize {
+    let a: Vec<char> = left.chars().collect();
+    let b: Vec<char> = right.chars().collect();
+
+    // d[i][j] 为 a 的前 i 个字符与 b 的前 j 个字符之间的距离
+    let mut d = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for (i, row) in d.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in d[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            d[i][j] = (d[i - 1][j] + 1)
+                .min(d[i][j - 1] + 1)
+                .min(d[i - 1][j - 1] + cost);
+
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                d[i][j] = d[i][j].min(d[i - 2][j - 2] + 1);
+            }
+        }
+    }
+
+    d[a.len()][b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{edit_distance, suggest};
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("fib", "fib"), 0);
+        assert_eq!(edit_distance("fbi", "fib"), 1);
+        assert_eq!(edit_distance("lte", "let"), 1);
+        assert_eq!(edit_distance("add", "and"), 1);
+        assert_eq!(edit_distance("foo", ""), 3);
+    }
+
+    #[test]
+    fn test_suggest() {
+        let candidates = ["fib", "lte", "let", "add", "and", "subvec"];
+        assert_eq!(suggest("fbi", candidates.into_iter()), vec!["fib"]);
+        assert_eq!(suggest("lte", candidates.into_iter()), vec!["let"]);
+        assert_eq!(suggest("adn", candidates.into_iter()), vec!["add", "and"]);
+        assert!(suggest("xyz", candidates.into_iter()).is_empty());
+    }
+}
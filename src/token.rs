@@ -1,8 +1,132 @@
+// 分词
+//
+// 括号 `(`, `)`, `[`, `]`, `{`, `}` 以及集合的起始符号 `#{` 是单独的 token，
+// 其余内容以空白字符或者括号分隔。
+//
+// 字符串字面量（如 `"a b"`）和字符字面量（如 `#\(`、`#\u{1F600}`）作为一个完整的 token
+// 原样返回，由 parser 负责解析其中的转义。
 pub fn tokenize(expr: &str) -> Vec<String> {
-    // 这里用了偷懒的方法来分词
-    expr.replace("(", " ( ")
-        .replace(")", " ) ")
-        .split_whitespace()
-        .map(|x| x.to_string())
-        .collect()
+    let mut tokens: Vec<String> = vec![];
+    let mut current = String::new();
+
+    let mut chars = expr.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                if !current.is_empty() {
+                    tokens.push(current.clone());
+                    current.clear();
+                }
+
+                // 读取至未被转义的双引号为止，如果缺少结尾的双引号则读取至末尾
+                let mut literal = String::from('"');
+                while let Some(sc) = chars.next() {
+                    literal.push(sc);
+                    match sc {
+                        '\\' => {
+                            if let Some(escaped) = chars.next() {
+                                literal.push(escaped);
+                            }
+                        }
+                        '"' => break,
+                        _ => {}
+                    }
+                }
+                tokens.push(literal);
+            }
+            '\\' if current == "#" => {
+                // 字符字面量，反斜杠后面的第一个字符无论是否括号或者空白都属于该字面量
+                current.push(c);
+                if let Some(first) = chars.next() {
+                    current.push(first);
+
+                    // Unicode 码点形式 `#\u{...}`
+                    if first == 'u' && chars.peek() == Some(&'{') {
+                        for uc in chars.by_ref() {
+                            current.push(uc);
+                            if uc == '}' {
+                                break;
+                            }
+                        }
+                    }
+                }
+            }
+            '{' if current == "#" => {
+                tokens.push("#{".to_string());
+                current.clear();
+            }
+            '(' | ')' | '[' | ']' | '{' | '}' => {
+                if !current.is_empty() {
+                    tokens.push(current.clone());
+                    current.clear();
+                }
+                tokens.push(c.to_string());
+            }
+            _ if c.is_whitespace() => {
+                if !current.is_empty() {
+                    tokens.push(current.clone());
+                    current.clear();
+                }
+            }
+            _ => current.push(c),
+        }
+    }
+
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+
+    tokens
+}
+
+// 如果脚本文件以 `#!` 开头（可执行脚本的 shebang 行，如 `#!/usr/bin/env toy`），则忽略第一行。
+// 只用于从文件读取的脚本（命令行运行的脚本以及 load-file），
+// read-string、eval 等处理的是程序内部的文本，不作特殊处理。
+pub fn strip_shebang(program: &str) -> &str {
+    match program.strip_prefix("#!") {
+        Some(rest) => rest.find('\n').map_or("", |idx| &rest[idx..]),
+        None => program,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{strip_shebang, tokenize};
+
+    #[test]
+    fn test_tokenize_list() {
+        assert_eq!(tokenize("(add 1 (mul 2 3))"), vec![
+            "(", "add", "1", "(", "mul", "2", "3", ")", ")"
+        ]);
+    }
+
+    #[test]
+    fn test_tokenize_vector() {
+        assert_eq!(tokenize("(count [1 2[3]])"), vec![
+            "(", "count", "[", "1", "2", "[", "3", "]", "]", ")"
+        ]);
+    }
+
+    #[test]
+    fn test_tokenize_map_and_set() {
+        assert_eq!(tokenize("{1 #{2}}"), vec!["{", "1", "#{", "2", "}", "}"]);
+    }
+
+    #[test]
+    fn test_tokenize_string_and_char() {
+        assert_eq!(tokenize(r#"(f "a (b)\" c" #\( #\space #\u{1F600})"#), vec![
+            "(", "f", r#""a (b)\" c""#, "#\\(", "#\\space", "#\\u{1F600}", ")"
+        ]);
+    }
+
+    #[test]
+    fn test_strip_shebang() {
+        assert_eq!(strip_shebang("#!/usr/bin/env toy\n(add 1 2)"), "\n(add 1 2)");
+        assert_eq!(strip_shebang("#!/usr/bin/env toy"), "");
+        // 只有位于开头的 `#!` 才是 shebang 行
+        assert_eq!(strip_shebang(" #!a"), " #!a");
+        // 分词本身不处理 shebang 行
+        assert_eq!(tokenize("#!a"), vec!["#!a"]);
+    }
 }
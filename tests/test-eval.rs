@@ -1,3 +1,6 @@
+// 基线的测试使用 `assert!(false)` 表示不应该到达的分支
+#![allow(clippy::assertions_on_constants)]
+
 use toy_lisp::{ast::Func, ast::Object, env::Environment, error::Error, eval::eval_from_string};
 
 fn internal_eval(program: &str) -> Result<Object, Error> {
@@ -6,6 +9,11 @@ fn internal_eval(program: &str) -> Result<Object, Error> {
     eval_from_string(program, &rc_env)
 }
 
+// 返回去除调用栈之后的错误
+fn internal_eval_error(program: &str) -> Error {
+    internal_eval(program).expect_err("expected error").into_root()
+}
+
 #[test]
 fn eval_base_expression() {
     let r1 = internal_eval("(add 1 2)").expect("eval failed");
@@ -212,4 +220,793 @@ fn eval_closure() {
     .expect("eval failed");
 
     assert!(matches!(r1, Object::Number(12)));
-}
\ No newline at end of file
+}
+
+#[test]
+fn eval_vector() {
+    let r1 = internal_eval("[1 (add 1 1) [3]]").expect("eval failed");
+    assert_eq!("[1 2 [3]]", r1.to_string());
+
+    let r2 = internal_eval("(count [1 2 3])").expect("eval failed");
+    assert!(matches!(r2, Object::Number(3)));
+
+    let r3 = internal_eval("(get [1 2 3] 1)").expect("eval failed");
+    assert!(matches!(r3, Object::Number(2)));
+
+    let r4 = internal_eval("(get [1 2 3] 3)").expect("eval failed");
+    assert!(matches!(r4, Object::Nil));
+}
+
+#[test]
+fn eval_vector_update() {
+    let r1 = internal_eval(
+        "\
+        (do
+            (let v1 [1 2 3])
+            (let v2 (assoc v1 0 10))
+            [v1 v2 (assoc v1 3 4) (conj v1 4 5)]
+        )
+        ",
+    )
+    .expect("eval failed");
+    assert_eq!("[[1 2 3] [10 2 3] [1 2 3 4] [1 2 3 4 5]]", r1.to_string());
+
+    let r2 = internal_eval("[(subvec [1 2 3 4] 1 3) (subvec [1 2 3 4] 2)]").expect("eval failed");
+    assert_eq!("[[2 3] [3 4]]", r2.to_string());
+
+    assert!(internal_eval("(subvec [1 2 3 4] 3 2)").is_err());
+
+    let e1 = internal_eval_error("(subvec [1 2 3] 1 0)");
+    assert_eq!("value error: subvec start index 1 is greater than end index 0", e1.to_string());
+}
+
+#[test]
+fn eval_map() {
+    let r1 = internal_eval("{1 (add 1 1) 3 [4]}").expect("eval failed");
+    assert_eq!("{1 2 3 [4]}", r1.to_string());
+
+    let r2 = internal_eval(
+        "\
+        (do
+            (let m (hash-map 1 true 2 false))
+            [(get m 1) (get m 3 false) (contains? m 2) (count m)]
+        )
+        ",
+    )
+    .expect("eval failed");
+    assert_eq!("[true false true 2]", r2.to_string());
+
+    let r3 = internal_eval(
+        "\
+        (do
+            (let m {1 10 2 20})
+            [(assoc m 3 30) (dissoc m 1) (keys m) (vals m) (merge m {2 200 4 40}) m]
+        )
+        ",
+    )
+    .expect("eval failed");
+    assert_eq!(
+        "[{1 10 2 20 3 30} {2 20} (1 2) (10 20) {1 10 2 200 4 40} {1 10 2 20}]",
+        r3.to_string()
+    );
+
+    // 映射表的键要求结构相等
+    let r4 = internal_eval("(get {[1 2] 3} [1 (add 1 1)])").expect("eval failed");
+    assert!(matches!(r4, Object::Number(3)));
+
+    let r5 = internal_eval("(get {1 2} 3)").expect("eval failed");
+    assert!(matches!(r5, Object::Nil));
+    assert!(internal_eval("{1 2 3}").is_err());
+
+    // 求值之后重复的键
+    let e1 = internal_eval_error("{(add 1 1) 1 2 2}");
+    assert_eq!("value error: duplicate key in map literal: 2", e1.to_string());
+}
+
+#[test]
+fn eval_set() {
+    let r1 = internal_eval("#{3 1 (add 1 1)}").expect("eval failed");
+    assert_eq!("#{1 2 3}", r1.to_string());
+
+    let r2 = internal_eval(
+        "\
+        (do
+            (let s1 #{1 2 3})
+            (let s2 #{2 3 4})
+            [(union s1 s2) (intersection s1 s2) (difference s1 s2) (conj s1 5) (contains? s1 1)]
+        )
+        ",
+    )
+    .expect("eval failed");
+    assert_eq!("[#{1 2 3 4} #{2 3} #{1} #{1 2 3 5} true]", r2.to_string());
+
+    assert!(internal_eval("#{1 1}").is_err());
+
+    let e1 = internal_eval_error("#{(add 1 1) 2}");
+    assert_eq!("value error: duplicate item in set literal: 2", e1.to_string());
+}
+
+#[test]
+fn eval_keyword() {
+    let r1 = internal_eval(":name").expect("eval failed");
+    assert_eq!(":name", r1.to_string());
+
+    let r2 = internal_eval("[(eq :name :name) (eq :name :age) (neq :name :age)]").expect("eval failed");
+    assert_eq!("[true false true]", r2.to_string());
+
+    let r3 = internal_eval(
+        "\
+        (do
+            (let person {:name 1 :age 20})
+            (let k :age)
+            [(:name person) (k person) (get person :name) (:email person 0)]
+        )
+        ",
+    )
+    .expect("eval failed");
+    assert_eq!("[1 20 1 0]", r3.to_string());
+
+    let r4 = internal_eval("(:email {:name 1})").expect("eval failed");
+    assert!(matches!(r4, Object::Nil));
+}
+
+#[test]
+fn eval_nil() {
+    let r1 = internal_eval("nil").expect("eval failed");
+    assert!(matches!(r1, Object::Nil));
+    assert_eq!("nil", r1.to_string());
+
+    let r2 = internal_eval("[(nil? nil) (nil? 1) (nil? (get {} 1))]").expect("eval failed");
+    assert_eq!("[true false true]", r2.to_string());
+
+    let r3 = internal_eval("[(eq nil nil) (eq nil 1) (eq false nil) (neq nil :name)]").expect("eval failed");
+    assert_eq!("[true false false true]", r3.to_string());
+
+    let r4 = internal_eval("(do)").expect("eval failed");
+    assert!(matches!(r4, Object::Nil));
+}
+
+#[test]
+fn eval_if_without_alternative() {
+    let r1 = internal_eval("(if true 1)").expect("eval failed");
+    assert!(matches!(r1, Object::Number(1)));
+
+    let r2 = internal_eval("(if false 1)").expect("eval failed");
+    assert!(matches!(r2, Object::Nil));
+
+    assert!(internal_eval("(if true)").is_err());
+}
+
+#[test]
+fn eval_char() {
+    let r1 = internal_eval(r"[#\a #\( #\space #\u{1F600}]").expect("eval failed");
+    assert_eq!("[a (   😀]", r1.to_string());
+
+    let r2 = internal_eval(
+        r"[(char->int #\a) (int->char 98) (char-upper #\c) (char-alphabetic? #\d) (char-numeric? #\1) (eq #\a #\a)]",
+    )
+    .expect("eval failed");
+    assert_eq!("[97 b C true true true]", r2.to_string());
+
+    assert!(internal_eval(r"#\foo").is_err());
+    assert!(internal_eval("(int->char -1)").is_err());
+}
+
+#[test]
+fn eval_string_and_char_list() {
+    let r1 = internal_eval(r#"(string->list "a b")"#).expect("eval failed");
+    assert_eq!("(a   b)", r1.to_string());
+
+    let r2 = internal_eval(r#"(list->string (string->list "a\"b"))"#).expect("eval failed");
+    assert!(matches!(r2, Object::String(s) if s == "a\"b"));
+
+    let r3 = internal_eval(r"(list->string [#\o #\k])").expect("eval failed");
+    assert!(matches!(r3, Object::String(s) if s == "ok"));
+
+    let r4 = internal_eval(r#"[(count "héllo") (eq "a" "a")]"#).expect("eval failed");
+    assert_eq!("[5 true]", r4.to_string());
+
+    assert!(internal_eval(r#""abc"#).is_err());
+}
+
+#[test]
+fn eval_error_kind() {
+    assert!(matches!(internal_eval_error("(add 1"), Error::ParseError(_)));
+    assert!(matches!(internal_eval_error("(let 1 2)"), Error::SyntaxError(_)));
+    assert!(matches!(internal_eval_error("foo"), Error::UnboundIdentifier { name, .. } if name == "foo"));
+    assert!(matches!(
+        internal_eval_error("(do (let a 1) (let a 2))"),
+        Error::AlreadyDefined(name) if name == "a"
+    ));
+    assert!(matches!(
+        internal_eval_error("(add 1 true)"),
+        Error::TypeError { function: Some(function), position: Some(2), expected, found, .. }
+            if function == "add" && expected == "number" && found == "bool"
+    ));
+    assert!(matches!(
+        internal_eval_error("(not true false)"),
+        Error::ArityError { expected, got: 2, .. } if expected == "1"
+    ));
+    assert!(matches!(
+        internal_eval_error("(do (defn f (a) a) (f 1 2))"),
+        Error::ArityError { name: Some(name), got: 2, .. } if name == "f"
+    ));
+    assert!(matches!(internal_eval_error("(div 1 0)"), Error::ArithmeticError(_)));
+    assert!(matches!(
+        internal_eval_error("(assoc [1 2] 5 0)"),
+        Error::IndexOutOfRange { index: 5, length: 2 }
+    ));
+
+    let e1 = internal_eval("(if 1 2 3)").unwrap_err();
+    assert_eq!("type error: expected bool, got number (1)", e1.to_string());
+}
+
+#[test]
+fn eval_throw() {
+    let r1 = internal_eval_error("(throw {:kind :not-found :id 1})");
+    assert!(matches!(r1, Error::UserError(Object::Map(_))));
+
+    let r2 = internal_eval_error("(do (throw 1) 2)");
+    assert!(matches!(r2, Error::UserError(Object::Number(1))));
+
+    // 抛出的值以可读的形式显示
+    let r3 = internal_eval_error(r#"(throw "a b")"#);
+    assert_eq!(r#"error: "a b""#, r3.to_string());
+
+    let r4 = internal_eval_error(r#"(throw {:msg "1"})"#);
+    assert_eq!(r#"error: {:msg "1"}"#, r4.to_string());
+}
+
+#[test]
+fn eval_try_catch() {
+    let r1 = internal_eval("(try (add 1 2) (catch _ e 0))").expect("eval failed");
+    assert!(matches!(r1, Object::Number(3)));
+
+    let r2 = internal_eval("(try (throw 42) (catch _ e (add e 1)))").expect("eval failed");
+    assert!(matches!(r2, Object::Number(43)));
+
+    // 按错误的种类匹配 catch 子句
+    let r3 = internal_eval(
+        "\
+        (try
+            (div 1 0)
+            (catch :type-error e 1)
+            (catch :arithmetic-error e (:kind e)))
+        ",
+    )
+    .expect("eval failed");
+    assert_eq!(":arithmetic-error", r3.to_string());
+
+    let r4 = internal_eval(
+        "\
+        (try
+            (throw {:kind :not-found :id 7})
+            (catch :not-found e (:id e)))
+        ",
+    )
+    .expect("eval failed");
+    assert!(matches!(r4, Object::Number(7)));
+
+    let r5 = internal_eval("(try (add 1 true) (catch :type-error e (:message e)))")
+        .expect("eval failed");
+    assert_eq!("add: argument 2 expected number, got bool (true)", r5.to_string());
+
+    // 错误信息里的值以可读的形式显示，字符串、字符保留引号和前缀
+    let r7 = internal_eval_error(r#"(add 1 "2")"#);
+    assert_eq!(r#"add: argument 2 expected number, got string ("2")"#, r7.to_string());
+
+    let r8 = internal_eval_error(r"(add 1 #\a)");
+    assert_eq!(r"add: argument 2 expected number, got char (#\a)", r8.to_string());
+
+    // 没有匹配的 catch 子句时，错误继续向外传播
+    let r6 = internal_eval_error("(try (throw 1) (catch :type-error e 0))");
+    assert!(matches!(r6, Error::UserError(Object::Number(1))));
+
+    assert!(matches!(
+        internal_eval_error("(try 1 (catch e 0))"),
+        Error::SyntaxError(_)
+    ));
+}
+
+#[test]
+fn eval_try_finally() {
+    // finally 子句总会执行，但其值被忽略
+    let r1 = internal_eval(
+        "\
+        (do
+            (let log (fn (x) (throw x)))
+            (try
+                (try 1 (finally (log :cleanup)))
+                (catch _ e e)))
+        ",
+    )
+    .expect("eval failed");
+    assert_eq!(":cleanup", r1.to_string());
+
+    let r2 = internal_eval("(try 1 (finally 2))").expect("eval failed");
+    assert!(matches!(r2, Object::Number(1)));
+
+    let r3 = internal_eval("(try (throw 1) (catch _ e (add e 1)) (finally 0))").expect("eval failed");
+    assert!(matches!(r3, Object::Number(2)));
+
+    let r4 = internal_eval_error("(try (throw 1) (finally 0))");
+    assert!(matches!(r4, Error::UserError(Object::Number(1))));
+}
+
+#[test]
+fn eval_stack_trace() {
+    let e1 = internal_eval(
+        "\
+        (do
+            (defn inner (x) (add x true))
+            (defn outer (x) (inner x))
+            (let call (fn (f) (f 1)))
+            (call outer)
+        )
+        ",
+    )
+    .expect_err("expected error");
+
+    assert_eq!(
+        vec!["(fn (f) (f 1))", "outer", "inner", "add"],
+        e1.stack_trace()
+    );
+    assert!(matches!(e1.root(), Error::TypeError { .. }));
+    assert_eq!("add: argument 2 expected number, got bool (true)", e1.to_string());
+
+    // 在函数外部发生的错误不带调用栈
+    let e2 = internal_eval("(if 1 2 3)").expect_err("expected error");
+    assert!(e2.stack_trace().is_empty());
+
+    // 嵌套的匿名函数以各自的源码区分
+    let e3 = internal_eval("(do (let call (fn (f) (f 1))) (call (fn (x) (add x true))))").expect_err("expected error");
+    assert_eq!(vec!["(fn (f) (f 1))", "(fn (x) (add x true))", "add"], e3.stack_trace());
+
+    // 参数个数不符以及解构失败的错误也带有被调用函数的调用栈
+    let e4 = internal_eval("(do (defn inc (x) (add x 1)) (defn twice (x) (inc x x)) (twice 1))").expect_err("expected error");
+    assert_eq!(vec!["twice", "inc"], e4.stack_trace());
+    assert!(matches!(e4.root(), Error::ArityError { .. }));
+
+    let e5 = internal_eval("(do (let first (fn ([a b]) a)) (first 1))").expect_err("expected error");
+    assert_eq!(vec!["(fn ([a b]) a)"], e5.stack_trace());
+}
+
+#[test]
+fn eval_unbound_identifier_suggestion() {
+    let e1 = internal_eval_error(
+        "\
+        (do
+            (defn fib (a) a)
+            (fbi 10)
+        )
+        ",
+    );
+    assert_eq!("identifier not found: fbi (did you mean: fib?)", e1.to_string());
+
+    let e2 = internal_eval_error("(do (let fool 1) (lte foo 1))");
+    assert!(matches!(e2, Error::UnboundIdentifier { suggestions, .. } if suggestions == vec!["fool"]));
+
+    // 特殊形式的名称也会被提示
+    let e3 = internal_eval_error("(do (lett a 1) a)");
+    assert_eq!("identifier not found: lett (did you mean: let, let*?)", e3.to_string());
+
+    let e4 = internal_eval_error("xyzzy");
+    assert_eq!("identifier not found: xyzzy", e4.to_string());
+}
+
+#[test]
+fn eval_equal() {
+    let r1 = internal_eval("[(eq [1 [2]] [1 [2]]) (eq [1 2] [2 1]) (neq [1] [1 2])]").expect("eval failed");
+    assert_eq!("[true false true]", r1.to_string());
+
+    let r2 = internal_eval("[(eq {:a 1 :b [2]} {:b [2] :a 1}) (eq {:a 1} {:a 2})]").expect("eval failed");
+    assert_eq!("[true false]", r2.to_string());
+
+    let r3 = internal_eval("[(eq #{1 2 3} #{3 2 1}) (eq #{1} #{1 2})]").expect("eval failed");
+    assert_eq!("[true false]", r3.to_string());
+
+    let r4 = internal_eval(r#"[(eq (read-string "(a 1)") (read-string "(a 1)")) (eq (read-string "(a)") (read-string "(b)"))]"#)
+        .expect("eval failed");
+    assert_eq!("[true false]", r4.to_string());
+
+    // 不同类型的数据不相等
+    let r5 = internal_eval("[(eq 1 true) (eq [1] (read-string \"(1)\")) (eq nil [])]").expect("eval failed");
+    assert_eq!("[false false false]", r5.to_string());
+
+    // 函数只跟同一个函数的副本相等，分别书写的相同源码的匿名函数不相等
+    let r6 = internal_eval(
+        "(do (let f (fn (x) x)) (let g (fn (x) x)) (let h f)
+             [(eq f h) (eq f g) (count #{f g}) (eq add add)])",
+    )
+    .expect("eval failed");
+    assert_eq!("[true false 2 true]", r6.to_string());
+
+    // 每次对 fn 表达式求值都产生新的函数
+    let r7 = internal_eval("(do (defn make () (fn (x) x)) (eq (make) (make)))").expect("eval failed");
+    assert_eq!("false", r7.to_string());
+}
+
+#[test]
+fn eval_builtin_type_error() {
+    let e1 = internal_eval_error("(sub 1 [1 2 3])");
+    assert_eq!("sub: argument 2 expected number, got vector ([1 2 3])", e1.to_string());
+
+    let e2 = internal_eval_error("(union #{1} #{2} 3)");
+    assert_eq!("union: argument 3 expected set, got number (3)", e2.to_string());
+
+    let e3 = internal_eval_error("(count 1)");
+    assert_eq!("count: argument 1 expected collection, got number (1)", e3.to_string());
+
+    let e4 = internal_eval_error("(not true false)");
+    assert_eq!("not: expected 1 argument, got 2", e4.to_string());
+
+    let e6 = internal_eval_error("(:name 1)");
+    assert_eq!(":name: argument 1 expected map, got number (1)", e6.to_string());
+}
+
+#[test]
+fn eval_type_of() {
+    let r1 = internal_eval(
+        r#"[(type-of nil) (type-of 1) (type-of true) (type-of #\a) (type-of "s") (type-of :k)
+            (type-of [1]) (type-of {}) (type-of #{}) (type-of add) (type-of (fn (x) x))]"#,
+    )
+    .expect("eval failed");
+    assert_eq!(
+        "[:nil :number :bool :char :string :keyword :vector :map :set :function :function]",
+        r1.to_string()
+    );
+}
+
+#[test]
+fn eval_format() {
+    let r1 = internal_eval(r#"(format "{} + {} = {}" 1 2 (add 1 2))"#).expect("eval failed");
+    assert_eq!("1 + 2 = 3", r1.to_string());
+
+    let r2 = internal_eval(r#"(format "{} {:?} {:?} {{}}" "a" "b\"c" [#\d #\space :e nil])"#)
+        .expect("eval failed");
+    assert_eq!(r#"a "b\"c" [#\d #\space :e nil] {}"#, r2.to_string());
+
+    let e1 = internal_eval_error(r#"(format "{} {}" 1)"#);
+    assert_eq!("value error: format template has 2 placeholders, got 1 argument", e1.to_string());
+
+    assert!(matches!(internal_eval_error(r#"(format "{x}" 1)"#), Error::ValueError(_)));
+    assert!(matches!(internal_eval_error(r#"(format "{")"#), Error::ValueError(_)));
+    assert!(matches!(internal_eval_error(r#"(format "}")"#), Error::ValueError(_)));
+    assert!(matches!(internal_eval_error("(format 1)"), Error::TypeError { .. }));
+}
+
+#[test]
+fn eval_eval() {
+    let r1 = internal_eval(r#"(eval (read-string "(add 1 (mul 2 3))"))"#).expect("eval failed");
+    assert_eq!("7", r1.to_string());
+
+    // 在当前环境里求值
+    let r2 = internal_eval(r#"(do (let a 10) (eval (read-string "(add a 1)")))"#).expect("eval failed");
+    assert_eq!("11", r2.to_string());
+
+    // 额外的绑定
+    let r3 = internal_eval(r#"(eval (read-string "(sub a b)") {:a 10 "b" 3})"#).expect("eval failed");
+    assert_eq!("7", r3.to_string());
+
+    let r4 = internal_eval(r#"(eval (read-string (pr-str [(add 1 2) "a"])))"#).expect("eval failed");
+    assert_eq!(r#"[3 "a"]"#, r4.to_readable_string());
+
+    // 没有额外的绑定时，表达式里的定义位于当前环境，eval 之后仍然可见
+    let r5 = internal_eval(r#"(do (eval (read-string "(let zz 1)")) (add zz 1))"#).expect("eval failed");
+    assert_eq!("2", r5.to_string());
+
+    let r6 = internal_eval(r#"(do (eval (read-string "(defn inc (x) (add x 1))")) (inc 1))"#)
+        .expect("eval failed");
+    assert_eq!("2", r6.to_string());
+
+    // 有额外的绑定时（即使是空的映射表），表达式在新的 scope 里求值，
+    // 其中的定义在求值期间可见，eval 之后不可见
+    let r7 = internal_eval(r#"(eval (read-string "(do (let zz (add a 1)) zz)") {:a 1})"#).expect("eval failed");
+    assert_eq!("2", r7.to_string());
+
+    let e1 = internal_eval_error(r#"(do (eval (read-string "(let zz 1)") {}) zz)"#);
+    assert_eq!("identifier not found: zz", e1.to_string());
+
+    let e2 = internal_eval_error(r#"(do (eval (read-string "(defn inc (x) (add x 1))") {}) (inc 1))"#);
+    assert!(matches!(e2, Error::UnboundIdentifier { .. }));
+
+    // 额外的绑定可以遮盖当前环境里的同名标识符
+    let r8 = internal_eval(r#"(do (let a 1) [(eval (read-string "a") {:a 2}) a])"#).expect("eval failed");
+    assert_eq!("[2 1]", r8.to_string());
+
+    // eval 是特殊形式，不能作为值传递
+    let e3 = internal_eval_error("(let f eval)");
+    assert_eq!("syntax error: eval is a special form and cannot be used as a value", e3.to_string());
+
+    assert!(matches!(internal_eval_error("(eval)"), Error::SyntaxError(_)));
+    assert!(matches!(internal_eval_error("(eval 1 2)"), Error::TypeError { .. }));
+    assert!(matches!(
+        internal_eval_error(r#"(eval (read-string "a") {:b 1})"#),
+        Error::UnboundIdentifier { .. }
+    ));
+}
+
+#[test]
+fn eval_arity_error() {
+    let e1 = internal_eval_error("(do (defn inc (x) (add x 1)) (inc 1 2))");
+    assert_eq!("inc: expected 1 argument, got 2", e1.to_string());
+
+    let e2 = internal_eval_error("(do (let f (fn (a b) (add a b))) (f 1))");
+    assert_eq!("(fn (a b) (add a b)): expected 2 arguments, got 1", e2.to_string());
+
+    let e3 = internal_eval_error("(do (let f (fn (a) (add a 100000000000 200000000000 300000000000))) (f))");
+    assert_eq!(
+        "(fn (a) (add a 100000000000 200000000000...: expected 1 argument, got 0",
+        e3.to_string()
+    );
+
+    // 函数的源码里的字面量以可读的形式显示
+    let e4 = internal_eval_error(r#"(do (let f (fn (s) (println "a b"))) (f))"#);
+    assert_eq!(r#"(fn (s) (println "a b")): expected 1 argument, got 0"#, e4.to_string());
+
+    let r1 = internal_eval(r#"(fn (a &optional (b "x") ({:keys (c)} {:c #\space})) [a "b"])"#).expect("eval failed");
+    assert_eq!(
+        r#"(fn (a &optional (b "x") ({:keys (c)} {:c #\space})) [a "b"])"#,
+        r1.to_string()
+    );
+}
+
+#[test]
+fn eval_arity() {
+    let r1 = internal_eval("(do (defn f (a b) a) [(arity f) (arity (fn () 1)) (arity add)])")
+        .expect("eval failed");
+    assert_eq!("[2 0 nil]", r1.to_string());
+
+    let e1 = internal_eval_error("(arity 1)");
+    assert_eq!("arity: argument 1 expected function, got number (1)", e1.to_string());
+}
+
+#[test]
+fn eval_variadic_and_optional_params() {
+    let r1 = internal_eval(
+        "(do (defn f (a &rest xs) [a xs]) [(f 1) (f 1 2 3)])",
+    )
+    .expect("eval failed");
+    assert_eq!("[[1 ()] [1 (2 3)]]", r1.to_string());
+
+    let r2 = internal_eval(
+        "(do (defn f (a &optional (b (add a 1)) c) [a b c]) [(f 1) (f 1 5) (f 1 5 6)])",
+    )
+    .expect("eval failed");
+    assert_eq!("[[1 2 nil] [1 5 nil] [1 5 6]]", r2.to_string());
+
+    let r3 = internal_eval(
+        "(do (let f (fn (a &key (size 10) color) [a size color])) [(f 1) (f 1 :color :red) (f 1 :color 2 :size 3)])",
+    )
+    .expect("eval failed");
+    assert_eq!("[[1 10 nil] [1 10 :red] [1 3 2]]", r3.to_string());
+
+    let r4 = internal_eval("(do (defn f (&rest xs &key size) [xs size]) (f :size 1))")
+        .expect("eval failed");
+    assert_eq!("[(:size 1) 1]", r4.to_string());
+
+    let r5 = internal_eval(
+        "(do (defn f (a &optional b) a) (defn g (&rest xs) xs) (defn h (&key k) k) [(arity f) (arity g) (arity h)])",
+    )
+    .expect("eval failed");
+    assert_eq!("[{:max 2 :min 1} {:max nil :min 0} {:max 2 :min 0}]", r5.to_string());
+
+    // README 里的例子
+    let r6 = internal_eval(
+        "(do (defn f (a &optional (b 10) c &rest others) [a b c others])
+             (defn g (a &rest others &key (size 1) color) [a others size color])
+             [(f 1) (g 1 :size 3)])",
+    )
+    .expect("eval failed");
+    assert_eq!("[[1 10 nil ()] [1 (:size 3) 3 nil]]", r6.to_string());
+
+    let e1 = internal_eval_error("(do (defn f (a &optional b) a) (f 1 2 3))");
+    assert_eq!("f: expected 1 to 2 arguments, got 3", e1.to_string());
+
+    let e2 = internal_eval_error("(do (defn f (a b &rest c) a) (f 1))");
+    assert_eq!("f: expected at least 2 arguments, got 1", e2.to_string());
+
+    let e3 = internal_eval_error("(do (defn f (&key size) size) (f :color 1))");
+    assert_eq!("value error: unknown keyword argument :color for f", e3.to_string());
+
+    let e4 = internal_eval_error("(do (defn f (&key size) size) (f 1 2))");
+    assert_eq!("f: argument 1 expected keyword, got number (1)", e4.to_string());
+}
+
+#[test]
+fn eval_malformed_params() {
+    let cases = [
+        ("(fn (a &rest) a)", "syntax error: expected a parameter name after the parameter marker"),
+        ("(fn (&rest a b) a)", "syntax error: expected only one parameter name after &rest"),
+        ("(fn (&rest a &optional b) a)", "syntax error: unexpected &optional in parameter list"),
+        ("(fn (&optional a &optional b) a)", "syntax error: unexpected &optional in parameter list"),
+        ("(fn (&rest &key a) a)", "syntax error: expected a parameter name after &rest"),
+        ("(fn (&foo a) a)", "syntax error: unknown parameter marker: &foo"),
+        ("(fn (a 1) a)", "syntax error: parameter name should be a string/symbol"),
+        ("(fn (a (b 1)) a)", "syntax error: invalid pattern: 1"),
+        ("(fn (a &rest (b c)) a)", "syntax error: parameter name should be a string/symbol"),
+        ("(fn (&optional (b)) b)", "syntax error: expected (name default) for parameter with default value"),
+        ("(fn (a &key a) a)", "syntax error: duplicate parameter name: a"),
+        // 可选参数会消耗掉 `:name value` 形式的实参，所以不能跟关键字参数同时使用
+        ("(fn (a &optional b &key c) a)", "syntax error: &optional and &key cannot be used in the same parameter list"),
+        ("(fn (&optional b &rest xs &key c) b)", "syntax error: &optional and &key cannot be used in the same parameter list"),
+        ("(fn (&optional &key c) c)", "syntax error: &optional and &key cannot be used in the same parameter list"),
+    ];
+
+    for (program, message) in cases {
+        assert_eq!(message, internal_eval_error(program).to_string(), "{}", program);
+    }
+}
+
+#[test]
+fn eval_multi_arity() {
+    let r1 = internal_eval(
+        "(do
+            (defn greet
+                ((name) (greet :hello name))
+                ((greeting name) [greeting name]))
+            [(greet :alice) (greet :hi :bob)])",
+    )
+    .expect("eval failed");
+    assert_eq!("[[:hello :alice] [:hi :bob]]", r1.to_string());
+
+    let r2 = internal_eval(
+        "(do
+            (let f (fn (() 0) ((a) 1) ((a b &rest xs) (count xs))))
+            [(f) (f 1) (f 1 2 3 4) (arity f)])",
+    )
+    .expect("eval failed");
+    assert_eq!("[0 1 2 [0 1 {:max nil :min 2}]]", r2.to_string());
+
+    let e1 = internal_eval_error("(do (defn f ((a) a) ((a b) b)) (f))");
+    assert_eq!("f: expected 1 or 2 arguments, got 0", e1.to_string());
+
+    let e2 = internal_eval_error("(defn f ((a) a) ((b) b))");
+    assert_eq!("syntax error: duplicate arity 1 in the clauses of the DEFN expression", e2.to_string());
+
+    let e3 = internal_eval_error("(fn ((a b) a) ((a &rest xs) a))");
+    assert_eq!("syntax error: duplicate arity 2 in the clauses of the FN expression", e3.to_string());
+
+    let e4 = internal_eval_error("(fn ((a) a b))");
+    assert_eq!("syntax error: expected (params body) for each clause of the FN expression", e4.to_string());
+}
+
+#[test]
+fn eval_destructuring() {
+    let r1 = internal_eval("(do (let f (fn ((a b) c) [a b c])) (f [1 2] 3))").expect("eval failed");
+    assert_eq!("[1 2 3]", r1.to_string());
+
+    let r3 = internal_eval("(do (defn f ([a [b c] &rest xs]) [a b c xs]) (f [1 [2 3] 4 5]))")
+        .expect("eval failed");
+    assert_eq!("[1 2 3 (4 5)]", r3.to_string());
+
+    let r4 = internal_eval(
+        "(let* (((x y) [1 2])
+                ({:keys (name age) (p q) :point} {:name :alice :point [3 4]}))
+            [x y name age p q])",
+    )
+    .expect("eval failed");
+    assert_eq!("[1 2 :alice nil 3 4]", r4.to_string());
+
+    let r5 = internal_eval("(do (let (a _ c) [1 2 3]) (let {:keys (d)} {:d 4}) [a c d])")
+        .expect("eval failed");
+    assert_eq!("[1 3 4]", r5.to_string());
+
+    let r6 = internal_eval("(do (defn f (&optional ((a b) [1 2])) [a b]) [(f) (f [3 4])])")
+        .expect("eval failed");
+    assert_eq!("[[1 2] [3 4]]", r6.to_string());
+
+    // let* 里后面的绑定可以遮盖前面的同名绑定，也可以遮盖外层的绑定
+    let r7 = internal_eval("(let* ((a 1) (a 2)) a)").expect("eval failed");
+    assert_eq!("2", r7.to_string());
+
+    let r8 = internal_eval("(let* ((a 1) (a (add a 1)) ((a b) [a 10])) [a b])").expect("eval failed");
+    assert_eq!("[2 10]", r8.to_string());
+
+    let r9 = internal_eval("(do (let a 1) (let* ((a 2)) a))").expect("eval failed");
+    assert_eq!("2", r9.to_string());
+
+    let e1 = internal_eval_error("(let* (((x y) [1 2 3])) x)");
+    assert_eq!(
+        "match error: cannot destructure [1 2 3] with pattern (x y): expected 2 items, got 3",
+        e1.to_string()
+    );
+
+    let e2 = internal_eval_error("(let* (((x &rest y) [])) x)");
+    assert_eq!(
+        "match error: cannot destructure [] with pattern (x &rest y): expected at least 1 item, got 0",
+        e2.to_string()
+    );
+
+    let e3 = internal_eval_error("(do (defn f ({:keys (a)}) a) (f [1]))");
+    assert_eq!(
+        "match error: cannot destructure [1] with pattern {:keys (a)}: expected map, got vector",
+        e3.to_string()
+    );
+
+    let e5 = internal_eval_error("(let (a b) 5)");
+    assert_eq!(
+        "match error: cannot destructure 5 with pattern (a b): expected list or vector, got number",
+        e5.to_string()
+    );
+
+    // 数据的种类跟模式不符也属于 :match-error
+    let r10 = internal_eval("(try (let {:keys (a)} 5) (catch :match-error e 1))").expect("eval failed");
+    assert_eq!("1", r10.to_string());
+
+    let e4 = internal_eval_error("(fn ((a b) (c a)) a)");
+    assert_eq!("syntax error: duplicate parameter name: a", e4.to_string());
+}
+
+#[test]
+fn eval_match() {
+    let r1 = internal_eval(
+        r#"(do
+            (defn calc (expr)
+                (match expr
+                    0 :zero
+                    (:add a b) (add (calc a) (calc b))
+                    (:neg x) (sub 0 (calc x))
+                    [x &rest _] :when (gt x 100) :big
+                    [x &rest xs] (count xs)
+                    {:keys (value)} value
+                    "s" :string
+                    n n))
+            [(calc 0)
+             (calc 5)
+             (calc [200 1])
+             (calc [1 2 3])
+             (calc {:value 7})
+             (calc "s")])"#,
+    )
+    .expect("eval failed");
+    assert_eq!("[:zero 5 :big 2 7 :string]", r1.to_string());
+
+    let r2 = internal_eval(
+        "(do
+            (defn role (user)
+                (match user
+                    {:admin :role} :all
+                    {:guest :role _ :name} nil
+                    _ :some))
+            [(role {:role :admin}) (role {:role :guest}) (role {:role :user})])",
+    )
+    .expect("eval failed");
+    assert_eq!("[:all nil :some]", r2.to_string());
+
+    let e1 = internal_eval_error("(match 3 1 :one 2 :two)");
+    assert_eq!("match error: non-exhaustive match: no pattern matches 3", e1.to_string());
+
+    let e5 = internal_eval_error(r#"(match "3" 3 :three)"#);
+    assert_eq!(r#"match error: non-exhaustive match: no pattern matches "3""#, e5.to_string());
+
+    let e6 = internal_eval_error(r#"(let (x) ["a" "b"])"#);
+    assert_eq!(
+        r#"match error: cannot destructure ["a" "b"] with pattern (x): expected 1 item, got 2"#,
+        e6.to_string()
+    );
+
+    let e2 = internal_eval_error("(match 3 (a a) 1)");
+    assert_eq!("syntax error: duplicate name in pattern: a", e2.to_string());
+
+    let e3 = internal_eval_error("(match 3 x :when 1 x)");
+    assert_eq!("type error: expected bool, got number (1)", e3.to_string());
+
+    let e4 = internal_eval_error("(match 3 x)");
+    assert_eq!(
+        "syntax error: expected an expression after the pattern of the MATCH expression",
+        e4.to_string()
+    );
+
+    let r3 = internal_eval("(try (match 3 1 :one) (catch :match-error e (:kind e)))").expect("eval failed");
+    assert_eq!(":match-error", r3.to_string());
+}
+
+#[test]
+fn eval_multiple_forms() {
+    let r1 = internal_eval("(let a 1) (let b (add a 1)) (mul b 3)").expect("eval failed");
+    assert_eq!("6", r1.to_string());
+
+    let e1 = internal_eval_error("(add 1 2) (add 3");
+    assert!(matches!(e1, Error::ParseError(_)));
+}
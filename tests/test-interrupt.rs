@@ -0,0 +1,24 @@
+// 中断标志是全局的，为了不影响其他测试，中断的测试单独放在这个文件（即单独的进程）里
+
+use toy_lisp::{
+    env::Environment,
+    error::Error,
+    eval::{clear_interrupt, eval_from_string, interrupt},
+};
+
+#[test]
+fn eval_interrupt() {
+    let rc_env = Environment::new_global().to_rc_env();
+
+    interrupt();
+    let e1 = eval_from_string("(add 1 2)", &rc_env).expect_err("expected error");
+    assert!(matches!(e1, Error::Interrupted));
+
+    // 中断不能被 try 表达式捕获
+    let e2 = eval_from_string("(try (add 1 2) (catch _ e 0))", &rc_env).expect_err("expected error");
+    assert!(matches!(e2.root(), Error::Interrupted));
+
+    clear_interrupt();
+    let r1 = eval_from_string("(add 1 2)", &rc_env).expect("eval failed");
+    assert_eq!("3", r1.to_string());
+}
@@ -0,0 +1,111 @@
+use std::{fs, path::PathBuf};
+
+use toy_lisp::{ast::Object, env::Environment, error::Error, eval::eval_from_string};
+
+fn internal_eval(program: &str) -> Result<Object, Error> {
+    let env = Environment::new_global_with_io();
+    let rc_env = env.to_rc_env();
+    eval_from_string(program, &rc_env)
+}
+
+fn internal_eval_error(program: &str) -> Error {
+    internal_eval(program).expect_err("expected error").into_root()
+}
+
+// 每个测试使用各自的临时目录，避免并行运行的测试互相影响
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("toy-lisp-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("create dir failed");
+    dir
+}
+
+#[test]
+fn io_read_and_write_file() {
+    let dir = temp_dir("read-write");
+    let path = dir.join("report.txt");
+    let path = path.display();
+
+    let r1 = internal_eval(&format!(
+        r#"(do
+            (spit "{0}" "a\nb")
+            (spit "{0}" 1 :append true)
+            [(file-exists? "{0}") (slurp "{0}") (read-lines "{0}")])"#,
+        path
+    ))
+    .expect("eval failed");
+    assert_eq!(r#"[true "a\nb1" ["a" "b1"]]"#, r1.to_readable_string());
+
+    let r2 = internal_eval(&format!(r#"(do (spit "{0}" "c") (slurp "{0}"))"#, path)).expect("eval failed");
+    assert_eq!("c", r2.to_string());
+
+    let r3 = internal_eval(&format!(r#"(do (delete-file "{0}") (file-exists? "{0}"))"#, path)).expect("eval failed");
+    assert_eq!("false", r3.to_string());
+
+    assert!(matches!(internal_eval_error(&format!(r#"(slurp "{}")"#, path)), Error::IoError(_)));
+    assert!(matches!(internal_eval_error(&format!(r#"(delete-file "{}")"#, path)), Error::IoError(_)));
+    assert!(matches!(
+        internal_eval_error(&format!(r#"(spit "{}" "a" :create true)"#, path)),
+        Error::ValueError(_)
+    ));
+
+    let r4 = internal_eval(&format!(
+        r#"(try (slurp "{}") (catch :io-error e :missing))"#,
+        path
+    ))
+    .expect("eval failed");
+    assert_eq!(":missing", r4.to_string());
+
+    fs::remove_dir_all(&dir).expect("remove dir failed");
+}
+
+#[test]
+fn io_list_dir() {
+    let dir = temp_dir("list-dir");
+    fs::write(dir.join("b.txt"), "").expect("write file failed");
+    fs::write(dir.join("a.txt"), "").expect("write file failed");
+    fs::create_dir(dir.join("c")).expect("create dir failed");
+
+    let r1 = internal_eval(&format!(r#"(list-dir "{}")"#, dir.display())).expect("eval failed");
+    assert_eq!(r#"["a.txt" "b.txt" "c"]"#, r1.to_readable_string());
+
+    fs::remove_dir_all(&dir).expect("remove dir failed");
+
+    assert!(matches!(
+        internal_eval_error(&format!(r#"(list-dir "{}")"#, dir.display())),
+        Error::IoError(_)
+    ));
+}
+
+#[test]
+fn io_load_file() {
+    let r1 = internal_eval(r#"(load-file "example/02-fib.cjs")"#).expect("eval failed");
+    assert_eq!("55", r1.to_string());
+
+    // 脚本里定义的函数在 load-file 之后可以使用
+    let dir = temp_dir("load-file");
+    let path = dir.join("square.cjs");
+    fs::write(&path, "#!/usr/bin/env toy\n(defn square (x) (mul x x))").expect("write file failed");
+
+    let r2 = internal_eval(&format!(r#"(do (load-file "{}") (square 5))"#, path.display())).expect("eval failed");
+    assert_eq!("25", r2.to_string());
+
+    fs::remove_dir_all(&dir).expect("remove dir failed");
+
+    assert!(matches!(internal_eval_error(r#"(load-file "no-such-file.cjs")"#), Error::IoError(_)));
+    assert!(matches!(internal_eval_error("(load-file 1)"), Error::TypeError { .. }));
+}
+
+#[test]
+fn io_disabled() {
+    // 默认的全局环境不允许访问文件系统
+    let rc_env = Environment::new_global().to_rc_env();
+
+    for name in ["slurp", "spit", "read-lines", "file-exists?", "list-dir", "delete-file"] {
+        let err = eval_from_string(name, &rc_env).expect_err("expected error");
+        assert!(matches!(err.into_root(), Error::UnboundIdentifier { .. }));
+    }
+
+    let err = eval_from_string(r#"(do (load-file "example/02-fib.cjs"))"#, &rc_env).expect_err("expected error");
+    assert!(matches!(err.into_root(), Error::IoError(_)));
+}
@@ -0,0 +1,36 @@
+use toy_lisp::{env::Environment, eval::eval_from_string, output::capture_output, run};
+
+fn internal_eval_output(program: &str) -> String {
+    let env = Environment::new_global();
+    let rc_env = env.to_rc_env();
+    let (result, text) = capture_output(|| eval_from_string(program, &rc_env));
+    result.expect("eval failed");
+    text
+}
+
+#[test]
+fn output_print() {
+    assert_eq!(internal_eval_output(r#"(print "a" 1 [#\b "c"])"#), "a 1 [b c]");
+    assert_eq!(internal_eval_output(r#"(do (println "a") (println) (print :b))"#), "a\n\n:b");
+    assert_eq!(
+        internal_eval_output(r#"(println (format "{} is {:?}" "name" "foo"))"#),
+        "name is \"foo\"\n"
+    );
+
+    // eprint 输出到标准错误，不会被捕获
+    assert_eq!(internal_eval_output(r#"(eprintln "error")"#), "");
+}
+
+#[test]
+fn output_literal_eval_order() {
+    // 映射表和集合字面量按源码的顺序求值，而不是按键排序之后的顺序
+    assert_eq!(internal_eval_output(r#"{:b (print "b") :a (print "a")}"#), "ba");
+    assert_eq!(internal_eval_output(r#"#{(do (print "b") 2) (do (print "a") 1)}"#), "ba");
+}
+
+#[test]
+fn output_run() {
+    let (status, text) = capture_output(|| run(r#"(println "hello") (add 1 2)"#, &[]));
+    assert_eq!(status, 0);
+    assert_eq!(text, "hello\n3\n");
+}
@@ -0,0 +1,67 @@
+use im_rc::{OrdMap, OrdSet, Vector};
+use proptest::prelude::*;
+use toy_lisp::{
+    ast::{Keyword, Object},
+    env::Environment,
+    eval::{eval_from_string, read_from_string},
+};
+
+// 可以作为标识符或者关键字名称的字符串（排除会被解析为数字、nil 和布尔值的名称）
+fn name() -> impl Strategy<Value = String> {
+    "[a-z*+!?<>=/_-][a-z0-9*+!?<>=/_-]{0,8}".prop_filter("name should not be a literal", |s| {
+        s.parse::<i64>().is_err() && !matches!(s.as_str(), "nil" | "true" | "false")
+    })
+}
+
+fn object() -> impl Strategy<Value = Object> {
+    let leaf = prop_oneof![
+        Just(Object::Nil),
+        any::<bool>().prop_map(Object::Bool),
+        any::<i64>().prop_map(Object::Number),
+        any::<char>().prop_map(Object::Char),
+        any::<String>().prop_map(Object::String),
+        name().prop_map(|n| Object::Keyword(Keyword::intern(&n))),
+        name().prop_map(Object::Symbol),
+    ];
+
+    leaf.prop_recursive(4, 64, 8, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..8).prop_map(Object::List),
+            prop::collection::vec(inner.clone(), 0..8)
+                .prop_map(|items| Object::Vector(items.into_iter().collect::<Vector<Object>>())),
+            prop::collection::vec((inner.clone(), inner.clone()), 0..8)
+                .prop_map(|entries| Object::Map(entries.into_iter().collect::<OrdMap<Object, Object>>().into())),
+            prop::collection::vec(inner, 0..8)
+                .prop_map(|items| Object::Set(items.into_iter().collect::<OrdSet<Object>>().into())),
+        ]
+    })
+}
+
+proptest! {
+    #[test]
+    fn printer_round_trip(obj in object()) {
+        let text = obj.to_readable_string();
+        let objects = read_from_string(&text).expect("read failed");
+        prop_assert_eq!(objects, vec![obj]);
+    }
+}
+
+#[test]
+fn printer_readable_and_display() {
+    let env = Environment::new_global();
+    let rc_env = env.to_rc_env();
+
+    let r1 = eval_from_string(r#"(pr-str "a\"b\n" #\c #\space [:d "e"] {"f" nil})"#, &rc_env)
+        .expect("eval failed");
+    assert_eq!(r1.to_string(), r#""a\"b\n" #\c #\space [:d "e"] {"f" nil}"#);
+
+    let r2 = eval_from_string(r#"(read-string (pr-str ["a" #\b #{1 2}]))"#, &rc_env).expect("eval failed");
+    assert_eq!(r2.to_readable_string(), r#"["a" #\b #{1 2}]"#);
+    assert_eq!(r2.to_string(), "[a b #{1 2}]");
+
+    let r3 = eval_from_string(r#"(read-string "(add 1 2)")"#, &rc_env).expect("eval failed");
+    assert_eq!(r3.to_string(), "(add 1 2)");
+
+    assert!(eval_from_string(r#"(read-string "1 2")"#, &rc_env).is_err());
+    assert!(eval_from_string(r#"(read-string "")"#, &rc_env).is_err());
+}
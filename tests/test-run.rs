@@ -0,0 +1,38 @@
+use toy_lisp::{run, EXIT_PARSE_ERROR, EXIT_RUNTIME_ERROR, EXIT_SUCCESS};
+
+#[test]
+fn run_exit_status() {
+    assert_eq!(run("(add 1 2)", &[]), EXIT_SUCCESS);
+    assert_eq!(run("(add 1 2) (sub 3 1)", &[]), EXIT_SUCCESS);
+
+    assert_eq!(run("(add 1", &[]), EXIT_PARSE_ERROR);
+    assert_eq!(run("(add 1 2) (add 1", &[]), EXIT_PARSE_ERROR);
+
+    assert_eq!(run("(add 1 true)", &[]), EXIT_RUNTIME_ERROR);
+    assert_eq!(run("foo", &[]), EXIT_RUNTIME_ERROR);
+}
+
+#[test]
+fn run_exit() {
+    assert_eq!(run("(exit)", &[]), EXIT_SUCCESS);
+    assert_eq!(run("(exit 3) (unknown)", &[]), 3);
+    assert_eq!(run("#!/usr/bin/env toy\n(add 1 2)", &[]), EXIT_SUCCESS);
+    // 只有脚本文件的 shebang 行被忽略，read-string 读取的文本保持原样，
+    // 所以这里读到两个表达式（符号 `#!/usr/bin/env` 和 `toy`）
+    assert_eq!(run("(read-string \"#!/usr/bin/env toy\")", &[]), EXIT_RUNTIME_ERROR);
+    assert_eq!(run("(read-string \"#!/usr/bin/env\")", &[]), EXIT_SUCCESS);
+
+    // exit 不能被 try 表达式捕获
+    assert_eq!(run("(try (exit 4) (catch _ e 0))", &[]), 4);
+    assert_eq!(run("(do (defn f () (exit 5)) (f))", &[]), 5);
+
+    assert_eq!(run("(exit 256)", &[]), EXIT_RUNTIME_ERROR);
+    assert_eq!(run("(exit true)", &[]), EXIT_RUNTIME_ERROR);
+}
+
+#[test]
+fn run_argv() {
+    let argv = vec!["foo".to_string(), "bar".to_string()];
+    assert_eq!(run("(if (eq (count *argv*) 2) 1 (unknown))", &argv), EXIT_SUCCESS);
+    assert_eq!(run("(if (eq (count *argv*) 0) 1 (unknown))", &[]), EXIT_SUCCESS);
+}